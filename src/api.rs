@@ -0,0 +1,447 @@
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    Json, Router,
+    extract::{
+        Path, Query, Request, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use tracing::{debug, info, warn};
+
+use crate::{
+    daemon::{ControlCommand, ControlRequest},
+    error::{AppError, Result},
+    events::{EVENT_BUS, EventEnvelope},
+    models::SkillConfig,
+    supervisor::{AccountState, SupervisorCommand},
+};
+
+/// Events kept for `GET /events`.
+const RECENT_EVENTS: usize = 500;
+
+/// Events kept from the bus for clients that poll instead of subscribing.
+#[derive(Clone, Default)]
+struct RecentEvents(Arc<Mutex<VecDeque<EventEnvelope>>>);
+
+impl RecentEvents {
+    fn collect(&self) {
+        let recent_events = self.clone();
+        let mut events = EVENT_BUS.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event_envelope) => {
+                        let mut buffered_events = recent_events.0.lock().unwrap();
+                        if buffered_events.len() == RECENT_EVENTS {
+                            buffered_events.pop_front();
+                        }
+                        buffered_events.push_back(event_envelope);
+                    }
+                    Err(RecvError::Lagged(skipped_events)) => {
+                        warn!(skipped_events, "Control API fell behind, events skipped.");
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        });
+    }
+}
+
+#[derive(Clone)]
+struct ApiState {
+    token: Arc<str>,
+    control: mpsc::Sender<ControlRequest>,
+    recent_events: RecentEvents,
+}
+
+/// An [`AppError`] answered with a JSON body and a matching status code.
+struct ApiError(AppError);
+
+impl From<AppError> for ApiError {
+    fn from(app_error: AppError) -> Self {
+        Self(app_error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self.0 {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Parse(_) | AppError::ParseInt(_) | AppError::Config(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(json!({ "error": self.0.to_string() }))).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+/// Compares in constant time so the token cannot be guessed byte by byte.
+fn tokens_match(given_token: &str, expected_token: &str) -> bool {
+    given_token.len() == expected_token.len()
+        && given_token
+            .bytes()
+            .zip(expected_token.bytes())
+            .fold(0, |difference, (given, expected)| {
+                difference | (given ^ expected)
+            })
+            == 0
+}
+
+async fn require_token(
+    State(api_state): State<ApiState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|authorization| authorization.to_str().ok())
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        .or_else(|| {
+            request
+                .uri()
+                .query()?
+                .split('&')
+                .find_map(|query_pair| query_pair.strip_prefix("access_token="))
+        })
+        .is_some_and(|given_token| tokens_match(given_token, &api_state.token));
+    if !is_authorized {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "Missing or invalid bearer token" })),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+async fn control(api_state: &ApiState, command: ControlCommand) -> ApiResult<Vec<AccountState>> {
+    Ok(ControlRequest::send(&api_state.control, command).await?)
+}
+
+async fn account_state(api_state: &ApiState, account_id: u64) -> ApiResult<AccountState> {
+    control(api_state, ControlCommand::States)
+        .await?
+        .into_iter()
+        .find(|account_state| account_state.account_id == account_id)
+        .ok_or_else(|| ApiError(AppError::NotFound(format!("account {account_id}"))))
+}
+
+async fn list_accounts(State(api_state): State<ApiState>) -> ApiResult<Json<Vec<AccountState>>> {
+    Ok(Json(control(&api_state, ControlCommand::States).await?))
+}
+
+async fn get_account(
+    State(api_state): State<ApiState>,
+    Path(account_id): Path<u64>,
+) -> ApiResult<Json<AccountState>> {
+    Ok(Json(account_state(&api_state, account_id).await?))
+}
+
+async fn get_character(
+    State(api_state): State<ApiState>,
+    Path(account_id): Path<u64>,
+) -> ApiResult<impl IntoResponse> {
+    let account_state = account_state(&api_state, account_id).await?;
+    Ok(Json(json!({
+        "character": account_state.character_info,
+        "location": account_state.location_name,
+    })))
+}
+
+async fn get_action(
+    State(api_state): State<ApiState>,
+    Path(account_id): Path<u64>,
+) -> ApiResult<impl IntoResponse> {
+    let account_state = account_state(&api_state, account_id).await?;
+    Ok(Json(json!({
+        "remaining_secs": account_state
+            .remaining_time()
+            .map(|remaining_time| remaining_time.num_seconds()),
+        "active_action": account_state.active_action,
+        "observed_at": account_state.observed_at,
+    })))
+}
+
+async fn send_command(
+    api_state: &ApiState,
+    account_id: u64,
+    command: SupervisorCommand,
+) -> ApiResult<(StatusCode, Json<Vec<AccountState>>)> {
+    let account_states = control(
+        api_state,
+        ControlCommand::Send {
+            account_id,
+            command,
+        },
+    )
+    .await?;
+    Ok((StatusCode::ACCEPTED, Json(account_states)))
+}
+
+async fn start_supervisor(
+    State(api_state): State<ApiState>,
+    Path(account_id): Path<u64>,
+) -> ApiResult<impl IntoResponse> {
+    let account_states = control(&api_state, ControlCommand::Start { account_id }).await?;
+    Ok((StatusCode::ACCEPTED, Json(account_states)))
+}
+
+/// `?now=true` aborts the supervisor instead of letting it finish its step.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct StopQuery {
+    now: bool,
+}
+
+async fn stop_supervisor(
+    State(api_state): State<ApiState>,
+    Path(account_id): Path<u64>,
+    Query(stop_query): Query<StopQuery>,
+) -> ApiResult<impl IntoResponse> {
+    if stop_query.now {
+        let account_states = control(&api_state, ControlCommand::StopNow { account_id }).await?;
+        return Ok((StatusCode::ACCEPTED, Json(account_states)));
+    }
+    send_command(&api_state, account_id, SupervisorCommand::Shutdown).await
+}
+
+async fn pause_supervisor(
+    State(api_state): State<ApiState>,
+    Path(account_id): Path<u64>,
+) -> ApiResult<impl IntoResponse> {
+    send_command(&api_state, account_id, SupervisorCommand::Pause).await
+}
+
+async fn resume_supervisor(
+    State(api_state): State<ApiState>,
+    Path(account_id): Path<u64>,
+) -> ApiResult<impl IntoResponse> {
+    send_command(&api_state, account_id, SupervisorCommand::Resume).await
+}
+
+async fn relogin(
+    State(api_state): State<ApiState>,
+    Path(account_id): Path<u64>,
+) -> ApiResult<impl IntoResponse> {
+    send_command(&api_state, account_id, SupervisorCommand::Relogin).await
+}
+
+#[derive(Deserialize, Debug)]
+struct SwitchRequest {
+    /// Character id or name.
+    character: String,
+}
+
+async fn switch_character(
+    State(api_state): State<ApiState>,
+    Path(account_id): Path<u64>,
+    Json(switch_request): Json<SwitchRequest>,
+) -> ApiResult<impl IntoResponse> {
+    send_command(
+        &api_state,
+        account_id,
+        SupervisorCommand::SwitchCharacter(switch_request.character),
+    )
+    .await
+}
+
+async fn set_skill_config(
+    State(api_state): State<ApiState>,
+    Path(account_id): Path<u64>,
+    Json(skill_config): Json<SkillConfig>,
+) -> ApiResult<impl IntoResponse> {
+    send_command(
+        &api_state,
+        account_id,
+        SupervisorCommand::SetSkillConfig(skill_config),
+    )
+    .await
+}
+
+/// Which events a client wants. Empty lists match everything.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct EventFilter {
+    accounts: Vec<u64>,
+    /// Event types, e.g. `level_up`.
+    types: Vec<String>,
+}
+
+impl EventFilter {
+    fn matches(&self, event_envelope: &EventEnvelope) -> bool {
+        (self.accounts.is_empty()
+            || event_envelope
+                .account_id
+                .is_some_and(|account_id| self.accounts.contains(&account_id)))
+            && (self.types.is_empty()
+                || self
+                    .types
+                    .iter()
+                    .any(|kind| kind == event_envelope.event.kind()))
+    }
+}
+
+/// `?account=1,2&type=level_up,action_failed`
+#[derive(Deserialize, Debug)]
+struct EventsQuery {
+    account: Option<String>,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    limit: Option<usize>,
+}
+
+impl EventsQuery {
+    fn filter(&self) -> Result<EventFilter> {
+        let split = |list: &Option<String>| -> Vec<String> {
+            list.iter()
+                .flat_map(|list| list.split(','))
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        Ok(EventFilter {
+            accounts: split(&self.account)
+                .iter()
+                .map(|account_id| account_id.parse())
+                .collect::<std::result::Result<_, _>>()?,
+            types: split(&self.kind),
+        })
+    }
+}
+
+async fn recent_events(
+    State(api_state): State<ApiState>,
+    Query(events_query): Query<EventsQuery>,
+) -> ApiResult<Json<Vec<EventEnvelope>>> {
+    let event_filter = events_query.filter()?;
+    let buffered_events = api_state.recent_events.0.lock().unwrap();
+    let mut matching_events: Vec<EventEnvelope> = buffered_events
+        .iter()
+        .rev()
+        .filter(|event_envelope| event_filter.matches(event_envelope))
+        .take(events_query.limit.unwrap_or(100))
+        .cloned()
+        .collect();
+    matching_events.reverse();
+    Ok(Json(matching_events))
+}
+
+/// Upgrades to a WebSocket that receives every matching event as a JSON text message.
+/// Sending `{"accounts": [...], "types": [...]}` replaces the filter.
+async fn stream_events(
+    websocket_upgrade: WebSocketUpgrade,
+    Query(events_query): Query<EventsQuery>,
+) -> ApiResult<Response> {
+    let event_filter = events_query.filter()?;
+    Ok(websocket_upgrade.on_upgrade(move |websocket| forward_events(websocket, event_filter)))
+}
+
+async fn forward_events(mut websocket: WebSocket, mut event_filter: EventFilter) {
+    let mut events = EVENT_BUS.subscribe();
+    debug!(?event_filter, "Event stream opened.");
+    loop {
+        let outgoing_message = tokio::select! {
+            received_event = events.recv() => match received_event {
+                Ok(event_envelope) if event_filter.matches(&event_envelope) => {
+                    serde_json::to_string(&event_envelope).ok()
+                }
+                Ok(_) => None,
+                Err(RecvError::Lagged(skipped_events)) => {
+                    warn!(skipped_events, "Event stream fell behind, events skipped.");
+                    Some(json!({ "type": "lagged", "skipped_events": skipped_events }).to_string())
+                }
+                Err(RecvError::Closed) => return,
+            },
+            incoming_message = websocket.recv() => match incoming_message {
+                Some(Ok(Message::Text(filter_text))) => {
+                    match serde_json::from_str::<EventFilter>(&filter_text) {
+                        Ok(new_filter) => {
+                            debug!(?new_filter, "Event stream filter changed.");
+                            event_filter = new_filter;
+                            None
+                        }
+                        Err(e) => {
+                            Some(json!({ "type": "error", "error": e.to_string() }).to_string())
+                        }
+                    }
+                }
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                Some(Ok(_)) => None,
+            },
+        };
+        if let Some(outgoing_message) = outgoing_message
+            && websocket
+                .send(Message::Text(outgoing_message.into()))
+                .await
+                .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Serves the control API on the given address until the process exits.
+/// Every request needs `Authorization: Bearer <token>`, or `?access_token=<token>` for
+/// browsers that cannot set headers on WebSocket requests.
+pub async fn serve(
+    listen_addr: SocketAddr,
+    token: String,
+    control: mpsc::Sender<ControlRequest>,
+) -> Result<()> {
+    if token.is_empty() {
+        return Err(AppError::Config(
+            "The control API needs a non-empty token".to_string(),
+        ));
+    }
+    let api_state = ApiState {
+        token: token.into(),
+        control,
+        recent_events: RecentEvents::default(),
+    };
+    api_state.recent_events.collect();
+
+    let router = Router::new()
+        .route("/accounts", get(list_accounts))
+        .route("/accounts/{account_id}", get(get_account))
+        .route("/accounts/{account_id}/character", get(get_character))
+        .route("/accounts/{account_id}/action", get(get_action))
+        .route("/accounts/{account_id}/start", post(start_supervisor))
+        .route("/accounts/{account_id}/stop", post(stop_supervisor))
+        .route("/accounts/{account_id}/pause", post(pause_supervisor))
+        .route("/accounts/{account_id}/resume", post(resume_supervisor))
+        .route("/accounts/{account_id}/relogin", post(relogin))
+        .route("/accounts/{account_id}/switch", post(switch_character))
+        .route("/accounts/{account_id}/skill", put(set_skill_config))
+        .route("/events", get(recent_events))
+        .route("/events/stream", get(stream_events))
+        .layer(middleware::from_fn_with_state(
+            api_state.clone(),
+            require_token,
+        ))
+        .with_state(api_state);
+
+    if !listen_addr.ip().is_loopback() {
+        warn!(%listen_addr, "Control API is reachable from other hosts.");
+    }
+    let listener = tokio::net::TcpListener::bind(listen_addr).await?;
+    info!(%listen_addr, "Serving control API.");
+    axum::serve(listener, router).await?;
+    Ok(())
+}
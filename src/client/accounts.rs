@@ -1,44 +1,66 @@
+use std::time::SystemTime;
+
 use async_trait::async_trait;
-use requestty::Question;
-use reqwest::{
-    cookie::CookieStore,
-    header::{self, HeaderValue},
-};
+use reqwest::cookie::CookieStore;
 use serde_json::json;
 use tracing::{debug, info, warn};
 
 use crate::{
     client::{IdleMMOClient, LocationApi},
     error::Result,
+    events::{BotEvent, EVENT_BUS},
+    metrics::METRICS,
     models::Account,
     parser::Parser,
+    replay::REPLAY,
+    transport::ObservedSend,
+    two_factor::TwoFactorRequest,
     utils::obfuscate_email,
 };
 
 #[allow(dead_code)]
 #[async_trait]
 pub trait AccountManagement {
-    async fn load_account(&mut self, account: Account) -> Result<()>;
+    async fn load_account(&mut self, account: Account) -> Result<bool>;
+    /// Like `load_account`, but keeps the account stored when its session expired.
+    async fn resume_session(&mut self, account: Account) -> Result<bool>;
     async fn get_account(&self) -> Result<Vec<Account>>;
     async fn add_account(&mut self, email: &str, password: &str) -> Result<()>;
     async fn post_login(&mut self, email: &str, password: &str) -> Result<()>;
+    async fn save_session(&self, account_id: u64) -> Result<()>;
 }
 #[async_trait]
 impl AccountManagement for IdleMMOClient {
     #[tracing::instrument(skip(self, account_to_load))]
-    async fn load_account(&mut self, account_to_load: Account) -> Result<()> {
+    async fn load_account(&mut self, account_to_load: Account) -> Result<bool> {
+        let account_id = account_to_load.id;
+        let is_session_valid = self.resume_session(account_to_load).await?;
+        if !is_session_valid {
+            warn!("Removing user with an invalid session from database.");
+            self.db_client.remove_user(account_id).await?;
+        }
+        Ok(is_session_valid)
+    }
+
+    #[allow(clippy::filter_next)]
+    #[tracing::instrument(skip(self, account_to_load))]
+    async fn resume_session(&mut self, account_to_load: Account) -> Result<bool> {
         info!(user_id = account_to_load.id, user_email = %obfuscate_email(&account_to_load.email), "Loading account.");
         self.update_client(&account_to_load.api_token)?;
 
         info!("Attempting to load account with stored cookie...");
+        // Seeded into the jar rather than sent as a header, so `save_session` writes the
+        // stored cookies back along with any the server sets.
+        for stored_cookie in account_to_load.cookie_str.split(';') {
+            let stored_cookie = stored_cookie.trim();
+            if !stored_cookie.is_empty() {
+                self.jar.add_cookie_str(stored_cookie, &self.base_url);
+            }
+        }
         let http_response = self
             .client
             .get(self.base_url.clone())
-            .header(
-                header::COOKIE,
-                HeaderValue::from_str(&account_to_load.cookie_str)?,
-            )
-            .send()
+            .send_observed(&self.request_defaults)
             .await?;
 
         let mut is_session_valid = false;
@@ -54,16 +76,29 @@ impl AccountManagement for IdleMMOClient {
                 self.update_current_data().await.is_ok() && self.get_locations(false).await.is_ok();
         }
 
+        if is_session_valid {
+            EVENT_BUS.publish(BotEvent::AccountLoaded {
+                account_id: account_to_load.id,
+                character: self.cache.character_info.name.clone(),
+            });
+        }
+
         if !is_session_valid {
-            warn!("Session cookie appears invalid. Removing user from database.");
-            self.db_client.remove_user(account_to_load.id).await?;
+            warn!("Session cookie appears invalid.");
+            METRICS.record_session_expired(account_to_load.id);
+            EVENT_BUS.publish(BotEvent::SessionExpired {
+                account_id: account_to_load.id,
+            });
         }
 
-        Ok(())
+        Ok(is_session_valid)
     }
 
     #[tracing::instrument(skip(self))]
     async fn get_account(&self) -> Result<Vec<Account>> {
+        if REPLAY.is_enabled() {
+            return Ok(vec![REPLAY.account()]);
+        }
         self.db_client.list_users().await
     }
 
@@ -71,6 +106,15 @@ impl AccountManagement for IdleMMOClient {
     async fn add_account(&mut self, email: &str, password: &str) -> Result<()> {
         self.update_current_data().await?;
         self.post_login(email, password).await?;
+        if self.dry_run {
+            // There is no session to extract a token or cookie from.
+            self.intercept(
+                "add_account",
+                "users",
+                &json!({ "email": email, "api_token": "<dry-run>", "cookie_str": "<dry-run>" }),
+            );
+            return Ok(());
+        }
 
         info!("Extracting API token and user metadata...");
         let extracted_api_token = Parser::ApiToken.get_value(&self.cache.html)?;
@@ -100,26 +144,38 @@ impl AccountManagement for IdleMMOClient {
             "password": password
         });
 
+        let login_url = format!("{}login", self.base_url);
+        if self.intercept(
+            "login",
+            &login_url,
+            &json!({ "email": email, "password": "<redacted>", "remember": "true" }),
+        ) {
+            return Ok(());
+        }
+
         let http_response = self
             .client
-            .post(format!("{}login", self.base_url))
+            .post(login_url)
             .form(&login_params)
-            .send()
+            .send_observed(&self.request_defaults)
             .await?;
         let mut response_html = http_response.text().await?;
-        let mut first_attempt = true;
+        let mut two_factor_request = TwoFactorRequest {
+            email: email.to_string(),
+            requested_at: SystemTime::now(),
+            attempt: 1,
+        };
         while let Ok(two_factor_auth_url) = Parser::TwoFactorUrl.get_value(&response_html) {
-            if first_attempt {
-                warn!("2FA Required: A code has been sent to your email. Please enter it below.");
-            } else {
-                warn!("Invalid 2FA code. Please try again.");
+            if two_factor_request.attempt > 1 {
+                warn!(attempt = two_factor_request.attempt, "Invalid 2FA code.");
+                METRICS.record_retry("two_factor");
             }
+            EVENT_BUS.publish(BotEvent::TwoFactorRequired {
+                email: obfuscate_email(email),
+                attempt: two_factor_request.attempt,
+            });
 
-            let two_factor_code = requestty::prompt([Question::int("2fa")
-                .message("Two-Factor Code:")
-                .build()])?["2fa"]
-                .as_int()
-                .unwrap();
+            let two_factor_code = self.two_factor.fetch_code(&two_factor_request).await?;
             info!("Submitting 2FA code...");
             let http_response = self
                 .client
@@ -128,16 +184,27 @@ impl AccountManagement for IdleMMOClient {
                     "_token": self.cache.csrf_token,
                     "code": two_factor_code
                 }))
-                .send()
+                .send_observed(&self.request_defaults)
                 .await?;
 
             response_html = http_response.text().await?;
             debug!(html_len = response_html.len(), "2FA response HTML received");
-            first_attempt = false;
+            two_factor_request.attempt += 1;
         }
 
         info!("2FA check passed (or was not required).");
 
         self.update_current_data().await
     }
+
+    #[tracing::instrument(skip(self))]
+    async fn save_session(&self, account_id: u64) -> Result<()> {
+        let Some(session_cookies) = self.jar.cookies(&self.base_url) else {
+            warn!("No session cookies to save.");
+            return Ok(());
+        };
+        self.db_client
+            .update_user_cookie(account_id, session_cookies.to_str()?)
+            .await
+    }
 }
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
 use serde_json::{Value, json};
 use tracing::{debug, info};
@@ -5,8 +7,14 @@ use tracing::{debug, info};
 use crate::{
     client::{IdleMMOClient, LocationApi},
     error::{AppError, Result},
-    models::{Action, FilterBy, SkillConfig, SkillItem, SkillType, location::Location},
+    events::{BotEvent, EVENT_BUS},
+    metrics::METRICS,
+    models::{
+        Action, FilterBy, Metrics, ResponseData, SkillConfig, SkillData, SkillItem, SkillType,
+        location::WorldLocation,
+    },
     parser::Parser,
+    transport::ObservedSend,
     utils::{API_VERSION, find_best_skill, generate_obfuscated_data},
 };
 
@@ -15,6 +23,7 @@ use crate::{
 pub trait ActionSkillApi {
     async fn start_skill(&mut self, config: SkillConfig) -> Result<()>;
     async fn get_active_action(&self) -> Result<Option<Action>>;
+    async fn get_skill_data(&self, skill_type: SkillType) -> Result<SkillData>;
 }
 
 #[async_trait]
@@ -27,20 +36,21 @@ impl ActionSkillApi for IdleMMOClient {
             find_best_skill(&available_locations, &config)
                 .ok_or_else(|| AppError::Application("No suitable skill found".to_string()))?;
 
-        if self.cache.character_info.location_id != selected_location.id {
-            self.move_location(
-                crate::models::location::TravelMode::Teleport,
-                selected_location.clone(),
-            )
+        let upcoming_action =
+            Duration::from_millis(selected_skill_item.wait_length_ms.unwrap_or_default());
+        self.travel_to(selected_location.clone(), upcoming_action)
             .await?;
-        }
 
-        dbg!(&selected_skill_item);
+        debug!(
+            skill_item = ?selected_skill_item.name,
+            skill_item_id = selected_skill_item.id,
+            "Skill item selected."
+        );
 
         let http_response = self
             .client
             .get(format!("{}skills/view/{}", self.base_url, config.skill_type).to_lowercase())
-            .send()
+            .send_observed(&self.request_defaults)
             .await?;
         let response_html = http_response.text().await?;
         let start_skill_api_url = Parser::SkillsStartApiEndpoint.get_value(&response_html)?;
@@ -55,14 +65,37 @@ impl ActionSkillApi for IdleMMOClient {
             "v": API_VERSION
         });
 
-        dbg!(&request_payload);
+        debug!(url = %start_skill_api_url, payload = %request_payload, "Calling API: Start Skill");
+        if self.intercept("start_skill", &start_skill_api_url, &request_payload) {
+            return Ok(());
+        }
         let http_response = self
             .client
             .post(start_skill_api_url)
             .json(&request_payload)
-            .send()
+            .send_observed(&self.request_defaults)
             .await?;
-        dbg!(&http_response.text().await?[..100]);
+        let response_status = http_response.status();
+        let response_body = http_response.text().await?;
+        debug!(
+            status = %response_status,
+            response = %response_body,
+            "Start skill response received."
+        );
+        if !response_status.is_success() {
+            let refusal_message = serde_json::from_str::<ResponseData>(&response_body)
+                .map(|response_message_data| response_message_data.message)
+                .unwrap_or(response_body);
+            return Err(AppError::Application(format!(
+                "Skill start refused ({response_status}): {refusal_message}"
+            )));
+        }
+        METRICS.record_action_started(&self.cache.character_info.name, &config.skill_type);
+        EVENT_BUS.publish(BotEvent::ActionStarted {
+            character: self.cache.character_info.name.clone(),
+            skill: config.skill_type.clone(),
+            item: selected_skill_item.name.clone().unwrap_or_default(),
+        });
         Ok(())
     }
 
@@ -77,7 +110,7 @@ impl ActionSkillApi for IdleMMOClient {
                 "character_id": self.cache.character_info.id,
                 "v": API_VERSION
             }))
-            .send()
+            .send_observed(&self.request_defaults)
             .await?;
 
         let json_response_data = http_api_response.json::<Value>().await?;
@@ -91,4 +124,51 @@ impl ActionSkillApi for IdleMMOClient {
             Ok(Some(active_action))
         }
     }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_skill_data(&self, skill_type: SkillType) -> Result<SkillData> {
+        let http_response = self
+            .client
+            .get(format!("{}skills/view/{}", self.base_url, skill_type).to_lowercase())
+            .send_observed(&self.request_defaults)
+            .await?;
+        let response_html = http_response.text().await?;
+        let skill_data_api_url = Parser::SkillsDataApiEndpoint.get_value(&response_html)?;
+        debug!(url = %skill_data_api_url, "Calling API: Get Skill Data");
+
+        let http_api_response = self
+            .client
+            .post(&skill_data_api_url)
+            .json(&json!({
+                "skill": skill_type.to_string().to_lowercase(),
+                "v": API_VERSION
+            }))
+            .send_observed(&self.request_defaults)
+            .await?;
+        let json_response_data = http_api_response.json::<Value>().await?;
+
+        let raw_skill_items = match json_response_data.get("items") {
+            Some(Value::Array(items)) => items.clone(),
+            Some(Value::Object(items)) => items.values().cloned().collect(),
+            _ => vec![],
+        };
+        let mut skill_items = Vec::with_capacity(raw_skill_items.len());
+        for raw_skill_item in raw_skill_items {
+            skill_items.push(serde_json::from_value::<SkillItem>(raw_skill_item)?);
+        }
+        let skill_metrics = json_response_data
+            .get("metrics")
+            .cloned()
+            .map(serde_json::from_value::<Metrics>)
+            .transpose()?
+            .unwrap_or_default();
+
+        info!(%skill_type, items = skill_items.len(), "Skill data fetched.");
+        METRICS.record_skill_metrics(&self.cache.character_info.name, &skill_type, &skill_metrics);
+        Ok(SkillData {
+            skill_type,
+            items: skill_items,
+            metrics: skill_metrics,
+        })
+    }
 }
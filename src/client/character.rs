@@ -4,9 +4,11 @@ use tracing::{debug, info};
 
 use crate::{
     client::IdleMMOClient,
-    error::Result,
+    error::{AppError, Result},
+    events::{BotEvent, EVENT_BUS},
     models::{Character, CharacterInfo, SkillType},
     parser::Parser,
+    transport::ObservedSend,
 };
 
 #[allow(dead_code)]
@@ -15,6 +17,8 @@ pub trait CharacterApi {
     async fn get_character_information(&mut self) -> Result<CharacterInfo>;
     async fn get_all_characters(&self) -> Result<Vec<Character>>;
     async fn switch_character(&mut self, character_to_switch: Character) -> Result<()>;
+    /// Finds one of the account's characters by id or name.
+    async fn find_character(&self, selector: &str) -> Result<Character>;
 }
 
 #[async_trait]
@@ -29,7 +33,7 @@ impl CharacterApi for IdleMMOClient {
             .client
             .post(&character_info_api_url)
             .json(&json!({}))
-            .send()
+            .send_observed(&self.request_defaults)
             .await?;
         let mut character_details = http_api_response.json::<CharacterInfo>().await?;
 
@@ -59,7 +63,7 @@ impl CharacterApi for IdleMMOClient {
             .client
             .post(&all_characters_api_url)
             .json(&json!({}))
-            .send()
+            .send_observed(&self.request_defaults)
             .await?;
         let raw_json_response = http_api_response.json::<Value>().await?;
 
@@ -84,23 +88,44 @@ impl CharacterApi for IdleMMOClient {
             return Ok(());
         }
 
+        let switch_url = format!(
+            "{}user/character/switch/{}",
+            self.base_url, character_to_switch.id
+        );
+        let switch_params = json!({
+            "_token": self.cache.csrf_token,
+            "return_to_current_page": false
+        });
+        if self.intercept("switch_character", &switch_url, &switch_params) {
+            return Ok(());
+        }
         self.client
-            .post(format!(
-                "{}user/character/switch/{}",
-                self.base_url, character_to_switch.id
-            ))
-            .form(&json!({
-                "_token": self.cache.csrf_token,
-                "return_to_current_page": false
-            }))
-            .send()
+            .post(switch_url)
+            .form(&switch_params)
+            .send_observed(&self.request_defaults)
             .await?;
 
         info!(
             name = %character_to_switch.name,
             id = character_to_switch.id,
             "Character switched.");
+        EVENT_BUS.publish(BotEvent::CharacterSwitched {
+            character_id: character_to_switch.id,
+            character: character_to_switch.name.clone(),
+        });
         self.update_current_data().await?;
         Ok(())
     }
+
+    #[tracing::instrument(skip(self))]
+    async fn find_character(&self, selector: &str) -> Result<Character> {
+        self.get_all_characters()
+            .await?
+            .into_iter()
+            .find(|candidate| {
+                candidate.id.to_string() == selector
+                    || candidate.name.eq_ignore_ascii_case(selector)
+            })
+            .ok_or_else(|| AppError::NotFound(format!("character {selector}")))
+    }
 }
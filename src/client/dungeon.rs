@@ -0,0 +1,252 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use tracing::{debug, info, warn};
+
+use crate::{
+    client::{ActionSkillApi, IdleMMOClient, LocationApi},
+    error::{AppError, Result},
+    events::{BotEvent, EVENT_BUS},
+    metrics::METRICS,
+    models::{
+        Action, Dungeon, DungeonFailure, DungeonFetch, DungeonRewards, SkillType,
+        location::WorldLocation,
+    },
+    parser::Parser,
+    transport::ObservedSend,
+    utils::{API_VERSION, generate_obfuscated_data},
+    world_cache::WORLD_CACHE,
+};
+
+#[allow(dead_code)]
+#[async_trait]
+pub trait DungeonApi {
+    /// Dungeons in every location, whether the character has unlocked them or not,
+    /// without cooldowns.
+    async fn get_dungeons(&mut self) -> Result<Vec<Dungeon>>;
+    /// Current details of `dungeon`, with the character's cooldown.
+    async fn get_dungeon(&self, dungeon: &Dungeon) -> Result<Dungeon>;
+    async fn enter_dungeon(&mut self, dungeon: &Dungeon) -> Result<()>;
+    async fn get_dungeon_progress(&self) -> Result<Option<Action>>;
+    async fn collect_dungeon_rewards(&mut self) -> Result<DungeonRewards>;
+}
+
+impl IdleMMOClient {
+    async fn get_dungeons_page(&self) -> Result<String> {
+        let http_response = self
+            .client
+            .get(format!("{}dungeons", self.base_url))
+            .send_observed(&self.request_defaults)
+            .await?;
+        Ok(http_response.text().await?)
+    }
+
+    /// Details of every dungeon in `locations` without cooldowns, bypassing every cache.
+    /// Dungeons whose details fail are reported instead of failing the rest.
+    pub(crate) async fn fetch_dungeon_details(
+        &self,
+        locations: &[WorldLocation],
+    ) -> Result<DungeonFetch> {
+        let dungeons_html = self.get_dungeons_page().await?;
+        let quick_view_api_url = Parser::QuickViewDungeonApiEndpoint.get_value(&dungeons_html)?;
+        debug!(url = %quick_view_api_url, "Calling API: Quick View Dungeon");
+
+        let mut dungeon_fetch = DungeonFetch::default();
+        for location in locations {
+            for dungeon_item in &location.dungeons {
+                match self
+                    .fetch_dungeon(&quick_view_api_url, dungeon_item.id)
+                    .await
+                {
+                    Ok(mut dungeon_details) => {
+                        dungeon_details.location_id = location.id;
+                        dungeon_fetch
+                            .dungeons
+                            .push(dungeon_details.without_cooldown());
+                    }
+                    Err(e) => {
+                        warn!(
+                            error = %e,
+                            dungeon = %dungeon_item.name,
+                            "Failed to fetch dungeon details. Skipping this entry."
+                        );
+                        dungeon_fetch.failures.push(DungeonFailure {
+                            dungeon_id: dungeon_item.id,
+                            error: e.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        dungeon_fetch
+            .dungeons
+            .sort_by_key(|dungeon| dungeon.level_required);
+        info!(count = dungeon_fetch.dungeons.len(), "Dungeons fetched.");
+        Ok(dungeon_fetch)
+    }
+
+    async fn fetch_dungeon(&self, quick_view_api_url: &str, dungeon_id: u64) -> Result<Dungeon> {
+        self.rate_limiter.acquire().await;
+        let quick_view_response = self
+            .client
+            .post(quick_view_api_url)
+            .json(&json!({ "dungeon_id": dungeon_id }))
+            .send_observed(&self.request_defaults)
+            .await?;
+        Ok(quick_view_response.json::<Dungeon>().await?)
+    }
+}
+
+#[async_trait]
+impl DungeonApi for IdleMMOClient {
+    #[tracing::instrument(skip(self))]
+    async fn get_dungeons(&mut self) -> Result<Vec<Dungeon>> {
+        if let Some(dungeons) = &self.cache.dungeons {
+            return Ok(dungeons.clone());
+        }
+
+        // Locations holding only dungeons are dropped from the per-character view.
+        let world_locations = self.get_world_locations(true).await?.locations;
+        let game_version = Parser::GameVersion.get_value(&self.cache.html).ok();
+        let dungeons = WORLD_CACHE
+            .dungeons(game_version.as_deref(), || {
+                self.fetch_dungeon_details(&world_locations)
+            })
+            .await?
+            .dungeons;
+        self.cache.dungeons = Some(dungeons.clone());
+        Ok(dungeons)
+    }
+
+    #[tracing::instrument(skip(self, dungeon), fields(dungeon = %dungeon.name))]
+    async fn get_dungeon(&self, dungeon: &Dungeon) -> Result<Dungeon> {
+        let dungeons_html = self.get_dungeons_page().await?;
+        let quick_view_api_url = Parser::QuickViewDungeonApiEndpoint.get_value(&dungeons_html)?;
+        let mut dungeon_details = self.fetch_dungeon(&quick_view_api_url, dungeon.id).await?;
+        dungeon_details.location_id = dungeon.location_id;
+        Ok(dungeon_details)
+    }
+
+    #[tracing::instrument(skip(self, dungeon), fields(dungeon = %dungeon.name))]
+    async fn enter_dungeon(&mut self, dungeon: &Dungeon) -> Result<()> {
+        let character_info = &self.cache.character_info;
+        if character_info.combat_level < dungeon.level_required {
+            return Err(AppError::Application(format!(
+                "Combat level {} is below the required level {} for {}",
+                character_info.combat_level, dungeon.level_required, dungeon.name
+            )));
+        }
+        if character_info.gold < dungeon.gold_cost {
+            return Err(AppError::Application(format!(
+                "Not enough gold to enter {} ({} < {})",
+                dungeon.name, character_info.gold, dungeon.gold_cost
+            )));
+        }
+
+        if self.cache.character_info.location_id != dungeon.location_id {
+            let dungeon_location = self
+                .get_world_locations(true)
+                .await?
+                .locations
+                .into_iter()
+                .find(|location| location.id == dungeon.location_id)
+                .ok_or_else(|| {
+                    AppError::Application(format!("Location of {} is unknown", dungeon.name))
+                })?;
+            let upcoming_action = Duration::from_millis(dungeon.wait_length_ms.unwrap_or_default());
+            self.travel_to(dungeon_location, upcoming_action).await?;
+        }
+
+        let dungeons_html = self.get_dungeons_page().await?;
+        let start_dungeon_api_url = Parser::DungeonsStartApiEndpoint.get_value(&dungeons_html)?;
+        debug!(url = %start_dungeon_api_url, "Calling API: Start Dungeon");
+
+        let start_dungeon_payload = json!({
+            "dungeon_id": dungeon.id,
+            "ts2mic5ytx": generate_obfuscated_data(None),
+            "qty6bx4peh": generate_obfuscated_data(None),
+            "v": API_VERSION
+        });
+        if self.intercept(
+            "enter_dungeon",
+            &start_dungeon_api_url,
+            &start_dungeon_payload,
+        ) {
+            return Ok(());
+        }
+        let http_response = self
+            .client
+            .post(start_dungeon_api_url)
+            .json(&start_dungeon_payload)
+            .send_observed(&self.request_defaults)
+            .await?
+            .error_for_status()?;
+        debug!(status = %http_response.status(), "Start dungeon response received.");
+
+        info!(level_required = dungeon.level_required, "Dungeon entered.");
+        METRICS.record_action_started(&self.cache.character_info.name, &SkillType::Dungeon);
+        EVENT_BUS.publish(BotEvent::ActionStarted {
+            character: self.cache.character_info.name.clone(),
+            skill: SkillType::Dungeon,
+            item: dungeon.name.clone(),
+        });
+        self.update_current_data().await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_dungeon_progress(&self) -> Result<Option<Action>> {
+        let active_action = self
+            .get_active_action()
+            .await?
+            .filter(|action| action.skill_type == SkillType::Dungeon);
+
+        if let Some(dungeon_action) = &active_action {
+            info!(
+                dungeon = %dungeon_action.item_name,
+                progress = dungeon_action.current_progress,
+                expires_in_secs = dungeon_action.expires_in.num_seconds(),
+                "Dungeon in progress."
+            );
+        }
+        Ok(active_action)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn collect_dungeon_rewards(&mut self) -> Result<DungeonRewards> {
+        let dungeons_html = self.get_dungeons_page().await?;
+        let claim_api_url = Parser::DungeonsClaimApiEndpoint.get_value(&dungeons_html)?;
+        debug!(url = %claim_api_url, "Calling API: Claim Dungeon Rewards");
+
+        let claim_payload = json!({
+            "character_id": self.cache.character_info.id,
+            "v": API_VERSION
+        });
+        if self.intercept("collect_dungeon_rewards", &claim_api_url, &claim_payload) {
+            return Ok(DungeonRewards::default());
+        }
+        let http_response = self
+            .client
+            .post(claim_api_url)
+            .json(&claim_payload)
+            .send_observed(&self.request_defaults)
+            .await?;
+        let json_response_data = http_response.json::<Value>().await?;
+        let dungeon_rewards = serde_json::from_value::<DungeonRewards>(json_response_data)?;
+
+        info!(
+            gold = dungeon_rewards.gold,
+            experience = dungeon_rewards.experience,
+            items = dungeon_rewards.items.len(),
+            "Dungeon rewards collected."
+        );
+        EVENT_BUS.publish(BotEvent::DungeonRewardsCollected {
+            character: self.cache.character_info.name.clone(),
+            gold: dungeon_rewards.gold,
+            experience: dungeon_rewards.experience,
+        });
+        self.update_current_data().await?;
+        Ok(dungeon_rewards)
+    }
+}
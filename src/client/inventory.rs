@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use tracing::{debug, info, warn};
+
+use crate::{
+    client::IdleMMOClient,
+    crystals::ESSENCE_CRYSTAL_ITEM_NAME,
+    error::{AppError, Result},
+    models::{InventoryItem, ResponseData},
+    parser::Parser,
+    transport::ObservedSend,
+    utils::API_VERSION,
+};
+
+#[allow(dead_code)]
+#[async_trait]
+pub trait InventoryApi {
+    async fn get_inventory(&self) -> Result<Vec<InventoryItem>>;
+    async fn use_item(&mut self, item: &InventoryItem) -> Result<()>;
+    async fn get_essence_crystal_count(&self) -> Result<u64>;
+}
+
+#[async_trait]
+impl InventoryApi for IdleMMOClient {
+    #[tracing::instrument(skip(self))]
+    async fn get_inventory(&self) -> Result<Vec<InventoryItem>> {
+        let inventory_api_url = Parser::InventoryAllApiEndpoint.get_value(&self.cache.html)?;
+        debug!(url = %inventory_api_url, "Calling API: Get Inventory");
+
+        let http_api_response = self
+            .client
+            .post(&inventory_api_url)
+            .json(&json!({
+                "character_id": self.cache.character_info.id,
+                "v": API_VERSION
+            }))
+            .send_observed(&self.request_defaults)
+            .await?;
+        let raw_json_response = http_api_response.json::<Value>().await?;
+
+        let raw_items = match raw_json_response.get("items").unwrap_or(&raw_json_response) {
+            Value::Array(items) => items.clone(),
+            Value::Object(items) => items.values().cloned().collect(),
+            _ => vec![],
+        };
+
+        let mut inventory_items = Vec::with_capacity(raw_items.len());
+        for raw_item in raw_items {
+            match serde_json::from_value::<InventoryItem>(raw_item) {
+                Ok(inventory_item) => inventory_items.push(inventory_item),
+                Err(e) => warn!(error = %e, "Failed to parse inventory item. Skipping this entry."),
+            }
+        }
+
+        info!(count = inventory_items.len(), "Inventory fetched.");
+        Ok(inventory_items)
+    }
+
+    #[tracing::instrument(skip(self, item), fields(item = %item.name))]
+    async fn use_item(&mut self, item: &InventoryItem) -> Result<()> {
+        if item.quantity == 0 {
+            return Err(AppError::Application(format!(
+                "No {} left to use",
+                item.name
+            )));
+        }
+
+        let use_item_api_url = Parser::InventoryUseApiEndpoint.get_value(&self.cache.html)?;
+        debug!(url = %use_item_api_url, "Calling API: Use Item");
+
+        let use_item_payload = json!({
+            "inventory_id": item.id,
+            "quantity": 1,
+            "v": API_VERSION
+        });
+        if self.intercept("use_item", &use_item_api_url, &use_item_payload) {
+            return Ok(());
+        }
+        let http_api_response = self
+            .client
+            .post(&use_item_api_url)
+            .json(&use_item_payload)
+            .send_observed(&self.request_defaults)
+            .await?;
+        let response_message_data = http_api_response.json::<ResponseData>().await?;
+
+        info!("{}", response_message_data.message);
+        Ok(())
+    }
+
+    /// Counts from the inventory rather than the cached character information, which
+    /// is not updated as crystals are spent.
+    #[tracing::instrument(skip(self))]
+    async fn get_essence_crystal_count(&self) -> Result<u64> {
+        let essence_crystals = self
+            .get_inventory()
+            .await?
+            .iter()
+            .filter(|inventory_item| inventory_item.name == ESSENCE_CRYSTAL_ITEM_NAME)
+            .map(|inventory_item| inventory_item.quantity)
+            .sum();
+        debug!(essence_crystals, "Essence crystals counted from inventory.");
+        Ok(essence_crystals)
+    }
+}
@@ -1,39 +1,61 @@
-use std::cmp::Reverse;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use futures::{StreamExt, stream};
 use serde_json::{Value, json};
 use tracing::{debug, info, warn};
 
 use crate::{
-    client::IdleMMOClient,
-    error::Result,
+    client::{ActionSkillApi, IdleMMOClient},
+    error::{AppError, Result},
+    events::{BotEvent, EVENT_BUS},
     models::{
-        ResponseData,
-        location::{Location, TravelMode},
+        ResponseData, SkillType,
+        location::{
+            LocationFailure, LocationFetch, LocationFilter, TravelMode, TravelOrigin, WorldLocation,
+        },
     },
     parser::Parser,
+    transport::ObservedSend,
+    travel::TravelPlan,
     utils::{API_VERSION, generate_obfuscated_data},
+    world_cache::WORLD_CACHE,
 };
 
 #[allow(dead_code)]
 #[async_trait]
 pub trait LocationApi {
-    async fn get_locations(&mut self, load_from_cache: bool) -> Result<Vec<Location>>;
-    async fn move_location(&mut self, travel_mode: TravelMode, location: Location) -> Result<()>;
+    /// Every location, unfiltered, with travel costs from where the character stands,
+    /// and those whose details failed.
+    async fn get_world_locations(&mut self, load_from_cache: bool) -> Result<LocationFetch>;
+    /// Locations with what the current character has unlocked.
+    async fn get_locations(&mut self, load_from_cache: bool) -> Result<Vec<WorldLocation>>;
+    async fn move_location(
+        &mut self,
+        travel_mode: TravelMode,
+        location: WorldLocation,
+    ) -> Result<()>;
+    async fn travel_to(&mut self, location: WorldLocation, upcoming_action: Duration)
+    -> Result<()>;
+    async fn wait_for_travel(&self) -> Result<()>;
 }
 
-#[async_trait]
-impl LocationApi for IdleMMOClient {
-    #[tracing::instrument(skip(self, load_from_cache))]
-    async fn get_locations(&mut self, load_from_cache: bool) -> Result<Vec<Location>> {
-        if load_from_cache && !self.cache.locations.is_empty() {
-            return Ok(self.cache.locations.clone());
-        }
+/// Quick-view requests in flight at once, on top of the client rate limit.
+const QUICK_VIEW_CONCURRENCY: usize = 4;
 
+impl IdleMMOClient {
+    /// Every location with its details, unfiltered, bypassing every cache. Locations whose
+    /// details fail are reported instead of failing the rest, unless all of them fail.
+    pub(crate) async fn fetch_all_locations(&self) -> Result<LocationFetch> {
         let all_locations_api_url = Parser::LocationsAllApiEndpoint.get_value(&self.cache.html)?;
         debug!(url = %all_locations_api_url, "Calling API: Get All Locations");
 
-        let http_response = self.client.post(all_locations_api_url).send().await?;
+        self.rate_limiter.acquire().await;
+        let http_response = self
+            .client
+            .post(all_locations_api_url)
+            .send_observed(&self.request_defaults)
+            .await?;
         let json_response_data: Value = http_response.json().await?;
 
         let raw_location_ids: Vec<u64> = json_response_data
@@ -45,47 +67,114 @@ impl LocationApi for IdleMMOClient {
             })
             .unwrap_or_default();
 
-        let total_raw_locations = raw_location_ids.len();
-        debug!(count = total_raw_locations, "Found initial locations.");
-        let mut filtered_locations = Vec::with_capacity(total_raw_locations);
-        if total_raw_locations == 0 {
+        debug!(count = raw_location_ids.len(), "Found initial locations.");
+        if raw_location_ids.is_empty() {
             warn!("No locations found in the initial fetch.");
-        } else {
-            let quick_view_api_url =
-                Parser::QuickViewLocationApiEndpoint.get_value(&self.cache.html)?;
-            for current_location_id in raw_location_ids {
-                let quick_view_response = self
-                    .client
-                    .post(&quick_view_api_url)
-                    .json(&json!({ "location_id": current_location_id }))
-                    .send()
-                    .await?;
+            return Ok(LocationFetch::default());
+        }
 
-                let mut current_location_details = quick_view_response.json::<Location>().await?;
-                current_location_details.enemies.retain(|current_enemy| {
-                    current_enemy.level >= self.cache.character_info.combat_level
-                });
-                current_location_details
-                    .skill_items
-                    .retain(|current_skill| {
-                        let character_skill_level = self
-                            .cache
-                            .character_info
-                            .skill_level
-                            .entry(current_skill.skill_type.clone())
-                            .or_default();
-                        *character_skill_level >= current_skill.level_required
+        let quick_view_api_url =
+            Parser::QuickViewLocationApiEndpoint.get_value(&self.cache.html)?;
+        // `buffered` yields in input order, so the result does not depend on timing.
+        let quick_view_results: Vec<(u64, Result<WorldLocation>)> = stream::iter(raw_location_ids)
+            .map(|location_id| {
+                let quick_view_api_url = &quick_view_api_url;
+                async move {
+                    let location_result = self
+                        .fetch_location_details(quick_view_api_url, location_id)
+                        .await;
+                    (location_id, location_result)
+                }
+            })
+            .buffered(QUICK_VIEW_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut location_fetch = LocationFetch::default();
+        for (location_id, location_result) in quick_view_results {
+            match location_result {
+                Ok(location_details) => location_fetch.locations.push(location_details),
+                Err(e) => {
+                    warn!(location_id, error = %e, "Failed to fetch location details.");
+                    location_fetch.failures.push(LocationFailure {
+                        location_id,
+                        error: e.to_string(),
                     });
-                let enemies_empty = current_location_details.enemies.is_empty();
-                let skill_items_empty = current_location_details.skill_items.is_empty();
-                if !enemies_empty || !skill_items_empty {
-                    filtered_locations.push(current_location_details);
                 }
             }
-            filtered_locations
-                .sort_by_key(|location_by_distance| Reverse(location_by_distance.distance));
-            self.cache.locations.clone_from(&filtered_locations);
         }
+
+        if location_fetch.locations.is_empty()
+            && let Some(first_failure) = location_fetch.failures.first()
+        {
+            return Err(AppError::Application(format!(
+                "Failed to fetch all {} locations, first: {}",
+                location_fetch.failures.len(),
+                first_failure.error
+            )));
+        }
+        if !location_fetch.is_complete() {
+            warn!(
+                fetched = location_fetch.locations.len(),
+                failed = location_fetch.failures.len(),
+                "Fetched locations partially."
+            );
+        }
+        Ok(location_fetch)
+    }
+
+    async fn fetch_location_details(
+        &self,
+        quick_view_api_url: &str,
+        location_id: u64,
+    ) -> Result<WorldLocation> {
+        self.rate_limiter.acquire().await;
+        let quick_view_response = self
+            .client
+            .post(quick_view_api_url)
+            .json(&json!({ "location_id": location_id }))
+            .send_observed(&self.request_defaults)
+            .await?;
+        Ok(quick_view_response.json::<WorldLocation>().await?)
+    }
+}
+
+#[async_trait]
+impl LocationApi for IdleMMOClient {
+    #[tracing::instrument(skip(self, load_from_cache))]
+    async fn get_world_locations(&mut self, load_from_cache: bool) -> Result<LocationFetch> {
+        let origin = TravelOrigin::of(&self.cache.character_info);
+        if load_from_cache
+            && !self.cache.locations.is_empty()
+            && self.cache.locations_origin == Some(origin)
+        {
+            return Ok(LocationFetch {
+                locations: self.cache.locations.clone(),
+                failures: self.cache.location_failures.clone(),
+            });
+        }
+
+        let game_version = Parser::GameVersion.get_value(&self.cache.html).ok();
+        let location_fetch = WORLD_CACHE
+            .locations(game_version.as_deref(), origin, || {
+                self.fetch_all_locations()
+            })
+            .await?;
+        if !location_fetch.locations.is_empty() {
+            self.cache.locations.clone_from(&location_fetch.locations);
+            self.cache
+                .location_failures
+                .clone_from(&location_fetch.failures);
+            self.cache.locations_origin = Some(origin);
+        }
+        Ok(location_fetch)
+    }
+
+    #[tracing::instrument(skip(self, load_from_cache))]
+    async fn get_locations(&mut self, load_from_cache: bool) -> Result<Vec<WorldLocation>> {
+        let world_locations = self.get_world_locations(load_from_cache).await?.locations;
+        let filtered_locations =
+            LocationFilter::unlocked_now(&self.cache.character_info).apply(&world_locations);
         info!(
             count = filtered_locations.len(),
             "Finished filtering locations."
@@ -94,7 +183,11 @@ impl LocationApi for IdleMMOClient {
     }
 
     #[tracing::instrument(skip(self, location, travel_mode))]
-    async fn move_location(&mut self, travel_mode: TravelMode, location: Location) -> Result<()> {
+    async fn move_location(
+        &mut self,
+        travel_mode: TravelMode,
+        location: WorldLocation,
+    ) -> Result<()> {
         info!(location = %location.name, ?travel_mode, "Attempting to move to new location.");
         let status_message = match travel_mode {
             TravelMode::Teleport => {
@@ -105,23 +198,34 @@ impl LocationApi for IdleMMOClient {
                         cost = location.teleport_cost,
                         "Teleport failed: Not enough gold."
                     );
-                    return Ok(());
+                    return Err(AppError::Travel(format!(
+                        "Not enough gold to teleport to {} ({} < {})",
+                        location.name, character_gold_amount, location.teleport_cost
+                    )));
                 }
 
+                let teleport_url = format!("{}locations/teleport/{}", self.base_url, location.key);
+                let teleport_params = json!({
+                    "_token": self.cache.csrf_token,
+                });
+                if self.intercept("teleport", &teleport_url, &teleport_params) {
+                    self.cache.character_info.location_id = location.id;
+                    return Ok(());
+                }
                 self.client
-                    .post(format!(
-                        "{}locations/teleport/{}",
-                        self.base_url, location.key
-                    ))
-                    .form(&json!({
-                        "_token": self.cache.csrf_token,
-                    }))
-                    .send()
+                    .post(teleport_url)
+                    .form(&teleport_params)
+                    .send_observed(&self.request_defaults)
                     .await?;
 
                 self.update_current_data().await?;
 
                 if character_gold_amount != self.cache.character_info.gold {
+                    EVENT_BUS.publish(BotEvent::Teleported {
+                        location_id: location.id,
+                        location: location.name.clone(),
+                        cost: character_gold_amount - self.cache.character_info.gold,
+                    });
                     "Teleport successful".to_string()
                 } else {
                     "You already at location".to_string()
@@ -130,18 +234,27 @@ impl LocationApi for IdleMMOClient {
             TravelMode::Walk => {
                 let travel_api_url =
                     Parser::LocationsTravelApiEndpoint.get_value(&self.cache.html)?;
+                let travel_payload = json!({
+                    "location_id": location.id,
+                    "ts2mic5ytx": generate_obfuscated_data(None),
+                    "qty6bx4peh": generate_obfuscated_data(None),
+                    "v": API_VERSION
+                });
+                if self.intercept("walk", &travel_api_url, &travel_payload) {
+                    self.cache.character_info.location_id = location.id;
+                    return Ok(());
+                }
                 let travel_http_response = self
                     .client
                     .post(travel_api_url)
-                    .json(&json!({
-                        "location_id": location.id,
-                        "ts2mic5ytx": generate_obfuscated_data(None),
-                        "qty6bx4peh": generate_obfuscated_data(None),
-                        "v": API_VERSION
-                    }))
-                    .send()
+                    .json(&travel_payload)
+                    .send_observed(&self.request_defaults)
                     .await?;
                 let response_message_data = travel_http_response.json::<ResponseData>().await?;
+                EVENT_BUS.publish(BotEvent::Travelled {
+                    location_id: location.id,
+                    location: location.name.clone(),
+                });
                 response_message_data.message
             }
         };
@@ -152,4 +265,47 @@ impl LocationApi for IdleMMOClient {
         );
         Ok(())
     }
+
+    #[tracing::instrument(skip(self, location, upcoming_action), fields(location = %location.name))]
+    async fn travel_to(
+        &mut self,
+        location: WorldLocation,
+        upcoming_action: Duration,
+    ) -> Result<()> {
+        let travel_plan =
+            self.travel_planner
+                .plan(&self.cache.character_info, &location, upcoming_action)?;
+        info!(?travel_plan, "Travel plan selected.");
+
+        match travel_plan {
+            TravelPlan::Stay => Ok(()),
+            TravelPlan::Teleport { .. } => self.move_location(TravelMode::Teleport, location).await,
+            TravelPlan::Walk { .. } => {
+                self.move_location(TravelMode::Walk, location).await?;
+                self.wait_for_travel().await?;
+                self.update_current_data().await
+            }
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn wait_for_travel(&self) -> Result<()> {
+        while let Some(active_action) = self.get_active_action().await? {
+            if active_action.skill_type != SkillType::Travelling {
+                break;
+            }
+            let remaining_time = active_action
+                .expires_in
+                .to_std()
+                .unwrap_or_default()
+                .max(Duration::from_secs(1));
+            info!(
+                remaining_secs = remaining_time.as_secs(),
+                "Walking, waiting for arrival."
+            );
+            tokio::time::sleep(remaining_time).await;
+        }
+        info!("Arrived at destination.");
+        Ok(())
+    }
 }
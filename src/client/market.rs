@@ -0,0 +1,390 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::{Value, json};
+use tracing::{debug, info, warn};
+
+use crate::{
+    client::{IdleMMOClient, InventoryApi},
+    error::{AppError, Result},
+    events::{BotEvent, EVENT_BUS},
+    models::{InventoryItem, MarketListing, MarketPolicy, SellMode, SkillItem, Trade, TradeSide},
+    parser::Parser,
+    transport::ObservedSend,
+    utils::{API_VERSION, generate_obfuscated_data},
+};
+
+#[allow(dead_code)]
+#[async_trait]
+pub trait MarketApi {
+    async fn get_listings(&self, item_id: u64) -> Result<Vec<MarketListing>>;
+    async fn buy_listing(&mut self, listing: &MarketListing, quantity: u64) -> Result<Trade>;
+    async fn create_listing(
+        &mut self,
+        item: &InventoryItem,
+        quantity: u64,
+        unit_price: u64,
+    ) -> Result<Trade>;
+    async fn vendor_sell(&mut self, item: &InventoryItem, quantity: u64) -> Result<Trade>;
+    async fn buy_missing_inputs(
+        &mut self,
+        skill_item: &SkillItem,
+        crafts: u64,
+        policy: &MarketPolicy,
+    ) -> Result<Vec<Trade>>;
+    async fn sell_surplus(&mut self, policy: &MarketPolicy) -> Result<Vec<Trade>>;
+}
+
+impl IdleMMOClient {
+    async fn get_market_page(&self) -> Result<String> {
+        let http_response = self
+            .client
+            .get(format!("{}market", self.base_url))
+            .send_observed(&self.request_defaults)
+            .await?;
+        Ok(http_response.text().await?)
+    }
+
+    fn new_trade(
+        &self,
+        item_id: u64,
+        item_name: &str,
+        quantity: u64,
+        unit_price: u64,
+        side: TradeSide,
+    ) -> Trade {
+        Trade {
+            at: Utc::now(),
+            character_id: self.cache.character_info.id,
+            item_id,
+            item_name: item_name.to_string(),
+            quantity,
+            unit_price,
+            side,
+        }
+    }
+
+    /// A trade that dry-run skipped: logged only, so neither the cached gold, the ledger
+    /// nor the event bus see it.
+    fn plan_trade(
+        &self,
+        item_id: u64,
+        item_name: &str,
+        quantity: u64,
+        unit_price: u64,
+        side: TradeSide,
+    ) -> Trade {
+        let trade = self.new_trade(item_id, item_name, quantity, unit_price, side);
+        info!(
+            item = %trade.item_name,
+            quantity,
+            unit_price,
+            ?side,
+            "Dry-run: trade planned."
+        );
+        trade
+    }
+
+    async fn record_trade(
+        &self,
+        item_id: u64,
+        item_name: &str,
+        quantity: u64,
+        unit_price: u64,
+        side: TradeSide,
+    ) -> Trade {
+        let trade = self.new_trade(item_id, item_name, quantity, unit_price, side);
+        info!(
+            item = %trade.item_name,
+            quantity,
+            unit_price,
+            ?side,
+            gold_delta = trade.gold_delta(),
+            "Trade completed."
+        );
+        if let Err(e) = self.db_client.insert_trade(&trade).await {
+            warn!(error = %e, "Failed to record trade in ledger.");
+        }
+        EVENT_BUS.publish(BotEvent::TradeCompleted {
+            trade: trade.clone(),
+        });
+        trade
+    }
+}
+
+#[async_trait]
+impl MarketApi for IdleMMOClient {
+    #[tracing::instrument(skip(self))]
+    async fn get_listings(&self, item_id: u64) -> Result<Vec<MarketListing>> {
+        let market_html = self.get_market_page().await?;
+        let listings_api_url = Parser::MarketListingsApiEndpoint.get_value(&market_html)?;
+        debug!(url = %listings_api_url, "Calling API: Get Market Listings");
+
+        let http_api_response = self
+            .client
+            .post(&listings_api_url)
+            .json(&json!({ "item_id": item_id, "v": API_VERSION }))
+            .send_observed(&self.request_defaults)
+            .await?;
+        let raw_json_response = http_api_response.json::<Value>().await?;
+
+        let mut market_listings = vec![];
+        if let Some(json_listings_array) =
+            raw_json_response.get("listings").and_then(|v| v.as_array())
+        {
+            for json_listing_value in json_listings_array.clone() {
+                match serde_json::from_value::<MarketListing>(json_listing_value) {
+                    Ok(market_listing) => market_listings.push(market_listing),
+                    Err(e) => {
+                        warn!(error = %e, "Failed to parse market listing. Skipping this entry.")
+                    }
+                }
+            }
+        }
+        market_listings.sort_by_key(|listing| listing.unit_price);
+
+        info!(count = market_listings.len(), "Market listings fetched.");
+        Ok(market_listings)
+    }
+
+    #[tracing::instrument(skip(self, listing), fields(item = %listing.item_name))]
+    async fn buy_listing(&mut self, listing: &MarketListing, quantity: u64) -> Result<Trade> {
+        let purchase_quantity = quantity.min(listing.quantity);
+        let total_cost = purchase_quantity.saturating_mul(listing.unit_price);
+        if self.cache.character_info.gold < total_cost {
+            return Err(AppError::Application(format!(
+                "Not enough gold to buy {} {} ({} < {})",
+                purchase_quantity, listing.item_name, self.cache.character_info.gold, total_cost
+            )));
+        }
+
+        let market_html = self.get_market_page().await?;
+        let buy_api_url = Parser::MarketBuyApiEndpoint.get_value(&market_html)?;
+        debug!(url = %buy_api_url, "Calling API: Buy Listing");
+
+        let buy_payload = json!({
+            "listing_id": listing.id,
+            "quantity": purchase_quantity,
+            "ts2mic5ytx": generate_obfuscated_data(None),
+            "qty6bx4peh": generate_obfuscated_data(None),
+            "v": API_VERSION
+        });
+        if self.intercept("buy_listing", &buy_api_url, &buy_payload) {
+            return Ok(self.plan_trade(
+                listing.item_id,
+                &listing.item_name,
+                purchase_quantity,
+                listing.unit_price,
+                TradeSide::Buy,
+            ));
+        }
+        self.client
+            .post(&buy_api_url)
+            .json(&buy_payload)
+            .send_observed(&self.request_defaults)
+            .await?
+            .error_for_status()?;
+
+        self.cache.character_info.gold -= total_cost;
+        Ok(self
+            .record_trade(
+                listing.item_id,
+                &listing.item_name,
+                purchase_quantity,
+                listing.unit_price,
+                TradeSide::Buy,
+            )
+            .await)
+    }
+
+    #[tracing::instrument(skip(self, item), fields(item = %item.name))]
+    async fn create_listing(
+        &mut self,
+        item: &InventoryItem,
+        quantity: u64,
+        unit_price: u64,
+    ) -> Result<Trade> {
+        let market_html = self.get_market_page().await?;
+        let create_listing_api_url =
+            Parser::MarketCreateListingApiEndpoint.get_value(&market_html)?;
+        debug!(url = %create_listing_api_url, "Calling API: Create Listing");
+
+        let create_listing_payload = json!({
+            "inventory_id": item.id,
+            "quantity": quantity,
+            "price": unit_price,
+            "v": API_VERSION
+        });
+        if self.intercept(
+            "create_listing",
+            &create_listing_api_url,
+            &create_listing_payload,
+        ) {
+            return Ok(self.plan_trade(
+                item.item_id,
+                &item.name,
+                quantity,
+                unit_price,
+                TradeSide::List,
+            ));
+        }
+        self.client
+            .post(&create_listing_api_url)
+            .json(&create_listing_payload)
+            .send_observed(&self.request_defaults)
+            .await?
+            .error_for_status()?;
+
+        Ok(self
+            .record_trade(
+                item.item_id,
+                &item.name,
+                quantity,
+                unit_price,
+                TradeSide::List,
+            )
+            .await)
+    }
+
+    #[tracing::instrument(skip(self, item), fields(item = %item.name))]
+    async fn vendor_sell(&mut self, item: &InventoryItem, quantity: u64) -> Result<Trade> {
+        let shop_html = self
+            .client
+            .get(format!("{}shop", self.base_url))
+            .send_observed(&self.request_defaults)
+            .await?
+            .text()
+            .await?;
+        let shop_sell_api_url = Parser::ShopSellApiEndpoint.get_value(&shop_html)?;
+        debug!(url = %shop_sell_api_url, "Calling API: Vendor Sell");
+
+        let gold_before_sale = self.cache.character_info.gold;
+        let vendor_sell_payload = json!({
+            "inventory_id": item.id,
+            "quantity": quantity,
+            "v": API_VERSION
+        });
+        if self.intercept("vendor_sell", &shop_sell_api_url, &vendor_sell_payload) {
+            // The vendor price is only known from the gold received.
+            return Ok(self.plan_trade(
+                item.item_id,
+                &item.name,
+                quantity,
+                0,
+                TradeSide::VendorSell,
+            ));
+        }
+        self.client
+            .post(&shop_sell_api_url)
+            .json(&vendor_sell_payload)
+            .send_observed(&self.request_defaults)
+            .await?
+            .error_for_status()?;
+
+        self.update_current_data().await?;
+        let gold_received = self
+            .cache
+            .character_info
+            .gold
+            .saturating_sub(gold_before_sale);
+        let unit_price = gold_received / quantity.max(1);
+        Ok(self
+            .record_trade(
+                item.item_id,
+                &item.name,
+                quantity,
+                unit_price,
+                TradeSide::VendorSell,
+            )
+            .await)
+    }
+
+    #[tracing::instrument(skip(self, skill_item, policy), fields(skill_item = ?skill_item.name))]
+    async fn buy_missing_inputs(
+        &mut self,
+        skill_item: &SkillItem,
+        crafts: u64,
+        policy: &MarketPolicy,
+    ) -> Result<Vec<Trade>> {
+        let inventory_items = self.get_inventory().await?;
+        let mut completed_trades = vec![];
+
+        for required_item in &skill_item.requirements {
+            let required_quantity = required_item.quantity_requirement.unwrap_or(1) * crafts;
+            let owned_quantity: u64 = inventory_items
+                .iter()
+                .filter(|inventory_item| inventory_item.item_id == required_item.id)
+                .map(|inventory_item| inventory_item.quantity)
+                .sum();
+            let mut missing_quantity = required_quantity.saturating_sub(owned_quantity);
+            if missing_quantity == 0 {
+                continue;
+            }
+
+            let required_item_name = required_item.name.clone().unwrap_or_default();
+            let price_cap = policy.price_cap(&required_item_name);
+            debug!(
+                item = %required_item_name,
+                missing_quantity,
+                price_cap,
+                "Buying missing recipe input."
+            );
+
+            for listing in self.get_listings(required_item.id).await? {
+                if missing_quantity == 0 || listing.unit_price > price_cap {
+                    break;
+                }
+                let trade = self.buy_listing(&listing, missing_quantity).await?;
+                missing_quantity -= trade.quantity;
+                completed_trades.push(trade);
+            }
+
+            if missing_quantity > 0 {
+                warn!(
+                    item = %required_item_name,
+                    missing_quantity,
+                    price_cap,
+                    "Could not buy all missing inputs under the price cap."
+                );
+            }
+        }
+
+        Ok(completed_trades)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn sell_surplus(&mut self, policy: &MarketPolicy) -> Result<Vec<Trade>> {
+        let inventory_items = self.get_inventory().await?;
+        let mut completed_trades = vec![];
+
+        for inventory_item in &inventory_items {
+            let surplus_quantity = inventory_item
+                .quantity
+                .saturating_sub(policy.keep_threshold(&inventory_item.name));
+            if surplus_quantity == 0 {
+                continue;
+            }
+
+            let trade_result = match policy.sell_mode {
+                SellMode::Vendor => self.vendor_sell(inventory_item, surplus_quantity).await,
+                SellMode::Market => {
+                    let cheapest_listing = self.get_listings(inventory_item.item_id).await?;
+                    match cheapest_listing.first() {
+                        Some(listing) => {
+                            let unit_price = listing.unit_price.saturating_sub(policy.undercut);
+                            self.create_listing(inventory_item, surplus_quantity, unit_price)
+                                .await
+                        }
+                        None => self.vendor_sell(inventory_item, surplus_quantity).await,
+                    }
+                }
+            };
+
+            match trade_result {
+                Ok(trade) => completed_trades.push(trade),
+                Err(e) => warn!(error = %e, item = %inventory_item.name, "Failed to sell surplus."),
+            }
+        }
+
+        Ok(completed_trades)
+    }
+}
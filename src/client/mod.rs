@@ -6,25 +6,38 @@ use reqwest::{
     cookie::Jar,
     header::{self, HeaderMap, HeaderValue},
 };
+use serde_json::Value;
 use tracing::{info, warn};
 
 use crate::{
     config::Config,
     db::DbClient,
     error::Result,
+    events::{BotEvent, EVENT_BUS},
+    metrics::METRICS,
     models::CachedData,
     parser::Parser,
+    replay::REPLAY,
+    transport::{ObservedSend, RateLimiter, RequestDefaults},
+    travel::TravelPlanner,
+    two_factor::TwoFactor,
 };
 
 pub mod accounts;
 pub mod actions;
 pub mod character;
+pub mod dungeon;
+pub mod inventory;
 pub mod location;
+pub mod market;
 
 pub use accounts::AccountManagement;
 pub use actions::ActionSkillApi;
 pub use character::CharacterApi;
+pub use dungeon::DungeonApi;
+pub use inventory::InventoryApi;
 pub use location::LocationApi;
+pub use market::MarketApi;
 
 #[derive(Debug)]
 pub struct IdleMMOClient {
@@ -33,6 +46,14 @@ pub struct IdleMMOClient {
     pub(crate) base_url: Url,
     pub(crate) cache: CachedData,
     pub(crate) db_client: DbClient,
+    pub(crate) travel_planner: TravelPlanner,
+    pub(crate) two_factor: TwoFactor,
+    /// Paces requests sent concurrently.
+    pub(crate) rate_limiter: RateLimiter,
+    /// What `client` adds to each request, kept for recording the headers actually sent.
+    pub(crate) request_defaults: RequestDefaults,
+    /// Mutating calls are logged and answered with a synthetic success instead of being sent.
+    pub(crate) dry_run: bool,
 
     user_agent: String,
 }
@@ -47,6 +68,12 @@ impl IdleMMOClient {
             .cookie_provider(Arc::clone(&jar))
             .user_agent(generated_user_agent.clone())
             .build()?;
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert(
+            header::USER_AGENT,
+            HeaderValue::from_str(&generated_user_agent)?,
+        );
+        let request_defaults = RequestDefaults::new(default_headers, Arc::clone(&jar));
         let app_config = Config::from_env()?;
         let db_client = DbClient::new(&app_config)?;
 
@@ -57,21 +84,47 @@ impl IdleMMOClient {
             db_client,
             base_url: Url::parse("https://web.idle-mmo.com")?,
             cache: CachedData::default(),
+            travel_planner: TravelPlanner::default(),
+            two_factor: TwoFactor::default(),
+            rate_limiter: RateLimiter::default(),
+            request_defaults,
+            dry_run: false,
             user_agent: generated_user_agent,
         })
     }
 
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        if dry_run {
+            info!("Dry-run enabled: mutating calls will be logged, not sent.");
+        }
+        self.dry_run = dry_run;
+        // A replayed session must never change the real account store.
+        self.db_client.dry_run = dry_run || REPLAY.is_enabled();
+    }
+
+    /// Logs a mutating call with its full payload when in dry-run mode.
+    /// Returns `true` if the caller must skip sending it.
+    pub(crate) fn intercept(&self, operation: &str, url: &str, payload: &Value) -> bool {
+        if self.dry_run {
+            info!(operation, %url, %payload, "Dry-run: intercepted mutating call.");
+        }
+        self.dry_run
+    }
+
     #[tracing::instrument(skip(self))]
     pub(crate) async fn update_current_data(&mut self) -> Result<()> {
-        let http_response = self.client.get(self.base_url.as_ref()).send().await?;
+        let http_response = self
+            .client
+            .get(self.base_url.as_ref())
+            .send_observed(&self.request_defaults)
+            .await?;
         let response_html = http_response.text().await?;
         let extracted_csrf_token = Parser::CsrfToken.get_value(&response_html)?;
 
         self.cache.html = response_html;
         self.cache.csrf_token = extracted_csrf_token;
-        match self.get_character_information().await {
-            Ok(character_information) => self.cache.character_info = character_information,
-            Err(e) => warn!(error = %e, "Failed to get character information during data update."),
+        if let Err(e) = self.refresh_character_info().await {
+            warn!(error = %e, "Failed to get character information during data update.");
         }
 
         info!(
@@ -82,6 +135,20 @@ impl IdleMMOClient {
         Ok(())
     }
 
+    /// Fetches the character information into the cache, publishing level ups and gold
+    /// changes since the cached copy.
+    pub(crate) async fn refresh_character_info(&mut self) -> Result<()> {
+        let character_information = self.get_character_information().await?;
+        METRICS.record_character(&character_information);
+        for character_event in
+            BotEvent::from_character_update(&self.cache.character_info, &character_information)
+        {
+            EVENT_BUS.publish(character_event);
+        }
+        self.cache.character_info = character_information;
+        Ok(())
+    }
+
     #[tracing::instrument(skip(self, api_token))]
     fn update_client(&mut self, api_token: &str) -> Result<()> {
         let mut default_headers = HeaderMap::new();
@@ -95,10 +162,13 @@ impl IdleMMOClient {
 
         self.client = ClientBuilder::new()
             .cookie_provider(Arc::clone(&self.jar))
-            .default_headers(default_headers)
+            .default_headers(default_headers.clone())
             .user_agent(self.user_agent.clone())
             .build()?;
 
+        default_headers.insert(header::USER_AGENT, HeaderValue::from_str(&self.user_agent)?);
+        self.request_defaults = RequestDefaults::new(default_headers, Arc::clone(&self.jar));
+
         info!("Reqwest client successfully rebuilt with updated default headers.");
         Ok(())
     }
@@ -0,0 +1,110 @@
+use std::{os::unix::fs::PermissionsExt, path::Path};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::mpsc,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    daemon::{ControlCommand, ControlRequest},
+    error::{AppError, Result},
+    output::SupervisorSummary,
+};
+
+/// One line of JSON per reply, matching one command per line.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+enum ControlReply {
+    Ok(Vec<SupervisorSummary>),
+    Error(String),
+}
+
+/// Binds the control socket, replacing a stale one left by a crashed daemon.
+/// Only the owner can connect.
+pub async fn bind(socket_path: &Path) -> Result<UnixListener> {
+    if tokio::fs::try_exists(socket_path).await? {
+        if UnixStream::connect(socket_path).await.is_ok() {
+            return Err(AppError::Config(format!(
+                "Another daemon is listening on {}",
+                socket_path.display()
+            )));
+        }
+        tokio::fs::remove_file(socket_path).await?;
+    }
+    let listener = UnixListener::bind(socket_path)?;
+    tokio::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(0o600)).await?;
+    info!(socket = %socket_path.display(), "Listening for control commands.");
+    Ok(listener)
+}
+
+/// Accepts connections until the task is aborted. Each connection may send any number
+/// of commands, one JSON `ControlCommand` per line.
+pub async fn serve(listener: UnixListener, control: mpsc::Sender<ControlRequest>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let control = control.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, control).await {
+                        debug!(error = %e, "Control connection closed.");
+                    }
+                });
+            }
+            Err(e) => warn!(error = %e, "Failed to accept control connection."),
+        }
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    control: mpsc::Sender<ControlRequest>,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let control_reply = match serde_json::from_str::<ControlCommand>(&line) {
+            Ok(command) => match ControlRequest::send(&control, command).await {
+                Ok(account_states) => {
+                    ControlReply::Ok(account_states.iter().map(SupervisorSummary::from).collect())
+                }
+                Err(e) => ControlReply::Error(e.to_string()),
+            },
+            Err(e) => ControlReply::Error(format!("Invalid command: {e}")),
+        };
+        let mut reply_line = serde_json::to_string(&control_reply)?;
+        reply_line.push('\n');
+        writer.write_all(reply_line.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// Sends one command to a running daemon and returns the affected supervisors.
+pub async fn send(socket_path: &Path, command: &ControlCommand) -> Result<Vec<SupervisorSummary>> {
+    let stream = UnixStream::connect(socket_path).await.map_err(|e| {
+        AppError::Application(format!(
+            "Cannot reach the daemon at {}: {e}",
+            socket_path.display()
+        ))
+    })?;
+    let (reader, mut writer) = stream.into_split();
+    let mut command_line = serde_json::to_string(command)?;
+    command_line.push('\n');
+    writer.write_all(command_line.as_bytes()).await?;
+    writer.shutdown().await?;
+
+    let reply_line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .ok_or_else(|| AppError::Application("Daemon closed the connection".to_string()))?;
+    match serde_json::from_str(&reply_line)? {
+        ControlReply::Ok(supervisor_summaries) => Ok(supervisor_summaries),
+        ControlReply::Error(message) => Err(AppError::Application(message)),
+    }
+}
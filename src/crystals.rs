@@ -0,0 +1,234 @@
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::models::SkillItem;
+
+pub const ESSENCE_CRYSTAL_ITEM_NAME: &str = "Essence Crystal";
+
+#[derive(Debug, Clone)]
+pub struct EssenceCrystalPolicy {
+    /// Crystals sent with each `start_skill` when the skill qualifies.
+    pub per_action: u64,
+    pub daily_cap: u64,
+    /// Skill items yielding less experience per hour never get crystals.
+    pub min_xp_per_hour: f64,
+}
+
+impl Default for EssenceCrystalPolicy {
+    fn default() -> Self {
+        Self {
+            per_action: 1,
+            daily_cap: 10,
+            min_xp_per_hour: 0.0,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct EssenceCrystalReport {
+    pub day: NaiveDate,
+    pub spent_today: u64,
+    pub remaining_today: u64,
+    pub total_spent: u64,
+    pub owned: u64,
+}
+
+/// What a character has spent, kept in the store so the daily cap survives restarts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EssenceCrystalSpend {
+    pub day: NaiveDate,
+    pub spent_today: u64,
+    pub total_spent: u64,
+}
+
+#[derive(Debug)]
+pub struct EssenceCrystalBudget {
+    pub policy: EssenceCrystalPolicy,
+    /// Character whose spend was restored from the store, if any.
+    pub character_id: Option<u64>,
+    day: NaiveDate,
+    spent_today: u64,
+    total_spent: u64,
+    last_known_owned: u64,
+}
+
+impl EssenceCrystalBudget {
+    pub fn new(policy: EssenceCrystalPolicy) -> Self {
+        Self {
+            policy,
+            character_id: None,
+            day: Local::now().date_naive(),
+            spent_today: 0,
+            total_spent: 0,
+            last_known_owned: 0,
+        }
+    }
+
+    /// Continues from `spend` for `character_id`, or from nothing when the character has
+    /// not spent any crystals yet.
+    pub fn restore(&mut self, character_id: u64, spend: Option<EssenceCrystalSpend>) {
+        let spend = spend.unwrap_or(EssenceCrystalSpend {
+            day: Local::now().date_naive(),
+            spent_today: 0,
+            total_spent: 0,
+        });
+        self.character_id = Some(character_id);
+        self.day = spend.day;
+        self.spent_today = spend.spent_today;
+        self.total_spent = spend.total_spent;
+    }
+
+    pub fn spend(&self) -> EssenceCrystalSpend {
+        EssenceCrystalSpend {
+            day: self.day,
+            spent_today: self.spent_today,
+            total_spent: self.total_spent,
+        }
+    }
+
+    fn roll_day(&mut self, today: NaiveDate) {
+        if today != self.day {
+            self.day = today;
+            self.spent_today = 0;
+        }
+    }
+
+    /// Crystals that may be spent on `skill_item` given `owned` crystals.
+    pub fn allowance(&mut self, skill_item: &SkillItem, owned: u64) -> u64 {
+        self.roll_day(Local::now().date_naive());
+        self.last_known_owned = owned;
+
+        let xp_per_hour = skill_item.experience_per_hour().unwrap_or_default();
+        if xp_per_hour < self.policy.min_xp_per_hour {
+            return 0;
+        }
+
+        let remaining_today = self.policy.daily_cap.saturating_sub(self.spent_today);
+        self.policy.per_action.min(remaining_today).min(owned)
+    }
+
+    pub fn record_spent(&mut self, amount: u64) {
+        if amount == 0 {
+            return;
+        }
+        self.roll_day(Local::now().date_naive());
+        self.spent_today += amount;
+        self.total_spent += amount;
+        self.last_known_owned = self.last_known_owned.saturating_sub(amount);
+
+        let report = self.report();
+        info!(
+            spent = amount,
+            spent_today = report.spent_today,
+            remaining_today = report.remaining_today,
+            total_spent = report.total_spent,
+            owned = report.owned,
+            "Essence crystals consumed."
+        );
+    }
+
+    pub fn report(&self) -> EssenceCrystalReport {
+        EssenceCrystalReport {
+            day: self.day,
+            spent_today: self.spent_today,
+            remaining_today: self.policy.daily_cap.saturating_sub(self.spent_today),
+            total_spent: self.total_spent,
+            owned: self.last_known_owned,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Days;
+
+    use super::*;
+
+    /// 60 experience every minute: 3600 per hour.
+    fn skill_item() -> SkillItem {
+        SkillItem {
+            wait_length_ms: Some(60_000),
+            experience: Some(60),
+            ..Default::default()
+        }
+    }
+
+    fn budget(per_action: u64, daily_cap: u64, min_xp_per_hour: f64) -> EssenceCrystalBudget {
+        EssenceCrystalBudget::new(EssenceCrystalPolicy {
+            per_action,
+            daily_cap,
+            min_xp_per_hour,
+        })
+    }
+
+    fn today() -> NaiveDate {
+        Local::now().date_naive()
+    }
+
+    #[test]
+    fn allowance_is_capped_by_per_action_and_owned() {
+        assert_eq!(budget(2, 10, 0.0).allowance(&skill_item(), 5), 2);
+        assert_eq!(budget(2, 10, 0.0).allowance(&skill_item(), 1), 1);
+    }
+
+    #[test]
+    fn allowance_is_capped_by_what_remains_today() {
+        let mut crystal_budget = budget(3, 10, 0.0);
+        crystal_budget.record_spent(8);
+        assert_eq!(crystal_budget.allowance(&skill_item(), 5), 2);
+        crystal_budget.record_spent(2);
+        assert_eq!(crystal_budget.allowance(&skill_item(), 5), 0);
+    }
+
+    #[test]
+    fn allowance_skips_slow_skill_items() {
+        assert_eq!(budget(1, 10, 3600.0).allowance(&skill_item(), 5), 1);
+        assert_eq!(budget(1, 10, 3601.0).allowance(&skill_item(), 5), 0);
+        // Without a wait length the experience rate is unknown, so it counts as zero.
+        assert_eq!(budget(1, 10, 1.0).allowance(&SkillItem::default(), 5), 0);
+    }
+
+    #[test]
+    fn allowance_continues_from_a_restored_spend() {
+        let mut crystal_budget = budget(3, 10, 0.0);
+        crystal_budget.restore(
+            7,
+            Some(EssenceCrystalSpend {
+                day: today(),
+                spent_today: 9,
+                total_spent: 40,
+            }),
+        );
+        assert_eq!(crystal_budget.character_id, Some(7));
+        assert_eq!(crystal_budget.allowance(&skill_item(), 5), 1);
+    }
+
+    #[test]
+    fn roll_day_resets_only_the_daily_spend() {
+        let mut crystal_budget = budget(3, 10, 0.0);
+        let yesterday = today() - Days::new(1);
+        crystal_budget.restore(
+            7,
+            Some(EssenceCrystalSpend {
+                day: yesterday,
+                spent_today: 10,
+                total_spent: 40,
+            }),
+        );
+
+        crystal_budget.roll_day(yesterday);
+        assert_eq!(crystal_budget.spend().spent_today, 10);
+
+        crystal_budget.roll_day(today());
+        assert_eq!(
+            crystal_budget.spend(),
+            EssenceCrystalSpend {
+                day: today(),
+                spent_today: 0,
+                total_spent: 40,
+            }
+        );
+        assert_eq!(crystal_budget.allowance(&skill_item(), 5), 3);
+    }
+}
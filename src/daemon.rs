@@ -0,0 +1,418 @@
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    sync::{
+        mpsc::{self, error::TrySendError},
+        oneshot,
+    },
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    client::{AccountManagement, IdleMMOClient},
+    control,
+    error::{AppError, Result},
+    models::Account,
+    profile::{PendingSkillConfigs, Profiles},
+    scheduler::SchedulerOptions,
+    supervisor::{
+        self, AccountState, Supervisor, SupervisorCommand, SupervisorHandle, SupervisorStatus,
+    },
+    utils::write_atomically,
+};
+
+#[derive(Debug, Clone)]
+pub struct DaemonOptions {
+    pub pid_file: PathBuf,
+    pub health_file: PathBuf,
+    /// Receives the final state of every supervisor on shutdown.
+    pub state_file: PathBuf,
+    pub health_interval: Duration,
+    /// How long supervisors get to finish their current step before being aborted.
+    pub shutdown_timeout: Duration,
+    /// Unix socket accepting control commands from the CLI.
+    pub control_socket: PathBuf,
+}
+
+impl Default for DaemonOptions {
+    fn default() -> Self {
+        Self {
+            pid_file: PathBuf::from("idlemmo-bot.pid"),
+            health_file: PathBuf::from("idlemmo-bot.health.json"),
+            state_file: PathBuf::from("idlemmo-bot.state.json"),
+            health_interval: Duration::from_secs(30),
+            shutdown_timeout: supervisor::SHUTDOWN_TIMEOUT,
+            control_socket: PathBuf::from("idlemmo-bot.sock"),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct AccountHealth {
+    account_id: u64,
+    status: SupervisorStatus,
+    observed_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct HealthReport {
+    pid: u32,
+    started_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    accounts: Vec<AccountHealth>,
+}
+
+/// What a control request asks the daemon to do.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlCommand {
+    /// State of every supervisor.
+    States,
+    /// Starts a supervisor for a stored account unless one is already running.
+    Start { account_id: u64 },
+    /// Aborts a supervisor without waiting for its current step or saving its session.
+    StopNow { account_id: u64 },
+    Send {
+        account_id: u64,
+        command: SupervisorCommand,
+    },
+}
+
+/// A command from outside the process, answered with the state of the affected supervisors.
+#[derive(Debug)]
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    pub reply: oneshot::Sender<Result<Vec<AccountState>>>,
+}
+
+impl ControlRequest {
+    /// Sends a command to the daemon and waits for its answer.
+    pub async fn send(
+        control: &mpsc::Sender<Self>,
+        command: ControlCommand,
+    ) -> Result<Vec<AccountState>> {
+        let (reply_sender, reply_receiver) = oneshot::channel();
+        let control_request = Self {
+            command,
+            reply: reply_sender,
+        };
+        let daemon_gone = || AppError::Application("Daemon is shutting down".to_string());
+        control
+            .send(control_request)
+            .await
+            .map_err(|_| daemon_gone())?;
+        reply_receiver.await.map_err(|_| daemon_gone())?
+    }
+}
+
+/// Waits for the next profile update, or forever when profiles are not watched.
+async fn recv_profiles(profile_updates: &mut Option<mpsc::Receiver<Profiles>>) -> Option<Profiles> {
+    match profile_updates {
+        Some(profile_updates) => profile_updates.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+pub struct Daemon {
+    options: DaemonOptions,
+    scheduler_options: SchedulerOptions,
+    /// Follow the whole account store on reload instead of the accounts started with.
+    all_accounts: bool,
+    handles: HashMap<u64, SupervisorHandle>,
+    started_at: DateTime<Utc>,
+    control_sender: mpsc::Sender<ControlRequest>,
+    control_receiver: mpsc::Receiver<ControlRequest>,
+    profiles: Profiles,
+    profile_updates: Option<mpsc::Receiver<Profiles>>,
+    /// Latest profile change per account whose supervisor was too busy to take it yet.
+    pending_skill_configs: PendingSkillConfigs,
+}
+
+impl Daemon {
+    pub fn new(
+        options: DaemonOptions,
+        scheduler_options: SchedulerOptions,
+        all_accounts: bool,
+        handles: Vec<SupervisorHandle>,
+    ) -> Self {
+        let (control_sender, control_receiver) = mpsc::channel(16);
+        Self {
+            options,
+            scheduler_options,
+            all_accounts,
+            handles: handles
+                .into_iter()
+                .map(|handle| (handle.account_id, handle))
+                .collect(),
+            started_at: Utc::now(),
+            control_sender,
+            control_receiver,
+            profiles: Profiles::default(),
+            profile_updates: None,
+            pending_skill_configs: PendingSkillConfigs::default(),
+        }
+    }
+
+    /// Starts supervisors with their skill from `profiles` and applies every update
+    /// received while running.
+    pub fn with_profiles(
+        mut self,
+        profiles: Profiles,
+        profile_updates: mpsc::Receiver<Profiles>,
+    ) -> Self {
+        self.profiles = profiles;
+        self.profile_updates = Some(profile_updates);
+        self
+    }
+
+    /// Sends control requests to the daemon, e.g. from the control API.
+    pub fn control(&self) -> mpsc::Sender<ControlRequest> {
+        self.control_sender.clone()
+    }
+
+    /// Runs until SIGTERM or SIGINT, reloading the account list on SIGHUP.
+    /// Returns the final state of every supervisor.
+    #[tracing::instrument(skip_all)]
+    pub async fn run(mut self, client: &IdleMMOClient) -> Result<Vec<AccountState>> {
+        let mut terminate_signal = signal(SignalKind::terminate())?;
+        let mut interrupt_signal = signal(SignalKind::interrupt())?;
+        let mut hangup_signal = signal(SignalKind::hangup())?;
+
+        let pid = std::process::id();
+        tokio::fs::write(&self.options.pid_file, format!("{pid}\n")).await?;
+        info!(pid, pid_file = %self.options.pid_file.display(), "Daemon started.");
+
+        let control_listener = control::bind(&self.options.control_socket).await?;
+        let control_task = tokio::spawn(control::serve(control_listener, self.control()));
+
+        let mut health_interval = tokio::time::interval(self.options.health_interval);
+        let mut profile_updates = self.profile_updates.take();
+        loop {
+            tokio::select! {
+                _ = terminate_signal.recv() => {
+                    info!("SIGTERM received.");
+                    break;
+                }
+                _ = interrupt_signal.recv() => {
+                    info!("SIGINT received.");
+                    break;
+                }
+                _ = hangup_signal.recv() => {
+                    info!("SIGHUP received, reloading accounts.");
+                    if let Err(e) = self.reload(client).await {
+                        error!(error = %e, "Reload failed, keeping current supervisors.");
+                    }
+                }
+                Some(control_request) = self.control_receiver.recv() => {
+                    let control_result = self.handle_control(client, control_request.command).await;
+                    control_request.reply.send(control_result).ok();
+                }
+                Some(profiles) = recv_profiles(&mut profile_updates) => {
+                    self.apply_profiles(profiles);
+                }
+                _ = health_interval.tick() => {
+                    self.send_pending_skill_configs();
+                    if let Err(e) = self.write_health().await {
+                        warn!(error = %e, "Failed to write health file.");
+                    }
+                }
+            }
+        }
+
+        control_task.abort();
+        let pid_file = self.options.pid_file.clone();
+        let control_socket = self.options.control_socket.clone();
+        let shutdown_result = self.shutdown().await;
+        if let Err(e) = tokio::fs::remove_file(&pid_file).await {
+            warn!(error = %e, "Failed to remove PID file.");
+        }
+        if let Err(e) = tokio::fs::remove_file(&control_socket).await {
+            warn!(error = %e, "Failed to remove control socket.");
+        }
+        shutdown_result
+    }
+
+    /// Stops supervisors for removed accounts, restarts finished ones and, with
+    /// `all_accounts`, starts supervisors for newly added accounts.
+    async fn reload(&mut self, client: &IdleMMOClient) -> Result<()> {
+        let stored_accounts = client.get_account().await?;
+
+        let removed_account_ids: Vec<u64> = self
+            .handles
+            .keys()
+            .filter(|account_id| {
+                !stored_accounts
+                    .iter()
+                    .any(|account| account.id == **account_id)
+            })
+            .copied()
+            .collect();
+        for account_id in removed_account_ids {
+            if let Some(handle) = self.handles.remove(&account_id) {
+                info!(
+                    account_id,
+                    "Account removed from store, stopping supervisor."
+                );
+                handle.commands.send(SupervisorCommand::Shutdown).await.ok();
+            }
+        }
+
+        for account in stored_accounts {
+            let needs_spawn = match self.handles.get(&account.id) {
+                Some(handle) => handle.task.is_finished(),
+                None => self.all_accounts,
+            };
+            if needs_spawn {
+                info!(account_id = account.id, "Starting supervisor.");
+                let handle = self.spawn(account)?;
+                self.handles.insert(handle.account_id, handle);
+            }
+        }
+
+        self.write_health().await
+    }
+
+    fn spawn(&self, account: Account) -> Result<SupervisorHandle> {
+        let account_options = self
+            .profiles
+            .scheduler_options(account.id, &self.scheduler_options);
+        Supervisor::spawn(account, account_options)
+    }
+
+    /// Sends changed skills to running supervisors, which switch at their next action.
+    fn apply_profiles(&mut self, profiles: Profiles) {
+        let account_ids: Vec<u64> = self.handles.keys().copied().collect();
+        let profile_changes = profiles.changes(
+            &self.profiles,
+            account_ids,
+            &self.scheduler_options.skill_config,
+        );
+        self.pending_skill_configs.extend(profile_changes);
+        self.profiles = profiles;
+        self.send_pending_skill_configs();
+    }
+
+    fn send_pending_skill_configs(&mut self) {
+        let handles = &self.handles;
+        self.pending_skill_configs
+            .send(|account_id| handles.get(&account_id).map(|handle| &handle.commands));
+    }
+
+    async fn handle_control(
+        &mut self,
+        client: &IdleMMOClient,
+        command: ControlCommand,
+    ) -> Result<Vec<AccountState>> {
+        info!(?command, "Control request received.");
+        match command {
+            ControlCommand::States => {
+                let mut account_states: Vec<AccountState> = self
+                    .handles
+                    .values()
+                    .map(SupervisorHandle::current_state)
+                    .collect();
+                account_states.sort_by_key(|account_state| account_state.account_id);
+                Ok(account_states)
+            }
+            ControlCommand::Start { account_id } => {
+                if let Some(handle) = self.handles.get(&account_id)
+                    && !handle.task.is_finished()
+                {
+                    return Ok(vec![handle.current_state()]);
+                }
+                let account = client
+                    .get_account()
+                    .await?
+                    .into_iter()
+                    .find(|account| account.id == account_id)
+                    .ok_or_else(|| AppError::NotFound(format!("account {account_id}")))?;
+                info!(account_id, "Starting supervisor.");
+                let handle = self.spawn(account)?;
+                let account_state = handle.current_state();
+                self.handles.insert(account_id, handle);
+                Ok(vec![account_state])
+            }
+            ControlCommand::StopNow { account_id } => {
+                let handle = self.handles.get_mut(&account_id).ok_or_else(|| {
+                    AppError::NotFound(format!("supervisor for account {account_id}"))
+                })?;
+                warn!(account_id, "Aborting supervisor.");
+                handle.task.abort();
+                (&mut handle.task).await.ok();
+                Ok(vec![handle.current_state()])
+            }
+            ControlCommand::Send {
+                account_id,
+                command,
+            } => {
+                let handle = self
+                    .handles
+                    .get(&account_id)
+                    .filter(|handle| !handle.task.is_finished())
+                    .ok_or_else(|| {
+                        AppError::NotFound(format!("running supervisor for account {account_id}"))
+                    })?;
+                // Never wait on a busy supervisor, control requests for others would stall.
+                handle.commands.try_send(command).map_err(|e| match e {
+                    TrySendError::Full(_) => AppError::Application(format!(
+                        "Supervisor {account_id} is busy, try again later"
+                    )),
+                    TrySendError::Closed(_) => {
+                        AppError::Application(format!("Supervisor {account_id} stopped"))
+                    }
+                })?;
+                Ok(vec![handle.current_state()])
+            }
+        }
+    }
+
+    async fn write_health(&self) -> Result<()> {
+        let mut accounts: Vec<AccountHealth> = self
+            .handles
+            .values()
+            .map(|handle| {
+                let account_state = handle.current_state();
+                AccountHealth {
+                    account_id: account_state.account_id,
+                    status: account_state.status,
+                    observed_at: account_state.observed_at,
+                    last_error: account_state.last_error,
+                }
+            })
+            .collect();
+        accounts.sort_by_key(|account_health| account_health.account_id);
+
+        let health_report = HealthReport {
+            pid: std::process::id(),
+            started_at: self.started_at,
+            updated_at: Utc::now(),
+            accounts,
+        };
+        write_atomically(
+            &self.options.health_file,
+            &serde_json::to_vec_pretty(&health_report)?,
+        )
+        .await
+    }
+
+    /// Asks every supervisor to save its session, waits for them and writes the final state.
+    async fn shutdown(self) -> Result<Vec<AccountState>> {
+        let final_states = supervisor::shutdown_all(
+            self.handles.into_values().collect(),
+            self.options.shutdown_timeout,
+        )
+        .await;
+
+        write_atomically(
+            &self.options.state_file,
+            &serde_json::to_vec_pretty(&final_states)?,
+        )
+        .await?;
+        info!(state_file = %self.options.state_file.display(), "Daemon stopped.");
+        Ok(final_states)
+    }
+}
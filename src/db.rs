@@ -1,16 +1,20 @@
-use serde_json::Value;
+use serde_json::{Value, json};
 use supabase_rs::SupabaseClient;
 use tracing::{debug, info, warn};
 
 use crate::{
     config::Config,
+    crystals::EssenceCrystalSpend,
     error::{AppError, Result},
-    models::Account,
+    models::{Account, Trade},
+    report::SessionReport,
 };
 
 #[derive(Clone, Debug)]
 pub struct DbClient {
     client: SupabaseClient,
+    /// Writes are logged and skipped instead of reaching the store.
+    pub(crate) dry_run: bool,
 }
 
 impl DbClient {
@@ -19,11 +23,26 @@ impl DbClient {
         let client = SupabaseClient::new(config.supabase_url.clone(), config.supabase_key.clone())
             .map_err(|e| AppError::SupabaseBuilder(e.to_string()))?;
         info!("Supabase client initialized.");
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            dry_run: false,
+        })
+    }
+
+    /// Logs a store write with its full payload when in dry-run mode.
+    /// Returns `true` if the caller must skip it.
+    fn intercept(&self, operation: &str, table: &str, payload: &Value) -> bool {
+        if self.dry_run {
+            info!(operation, table, %payload, "Dry-run: intercepted store write.");
+        }
+        self.dry_run
     }
 
     #[tracing::instrument(skip_all)]
     pub async fn remove_user(&self, user_id: u64) -> Result<()> {
+        if self.intercept("remove_user", "users", &json!({ "id": user_id })) {
+            return Ok(());
+        }
         self.client
             .delete("users", &user_id.to_string())
             .await
@@ -35,6 +54,9 @@ impl DbClient {
 
     #[tracing::instrument(skip(self, user))]
     pub async fn insert_user(&self, user: Value) -> Result<()> {
+        if self.intercept("insert_user", "users", &user) {
+            return Ok(());
+        }
         let inserted_id = self
             .client
             .insert("users", user)
@@ -45,6 +67,21 @@ impl DbClient {
         Ok(())
     }
 
+    #[tracing::instrument(skip(self, cookie_str))]
+    pub async fn update_user_cookie(&self, user_id: u64, cookie_str: &str) -> Result<()> {
+        let update_data = json!({ "cookie_str": cookie_str });
+        if self.intercept("update_user_cookie", "users", &update_data) {
+            return Ok(());
+        }
+        self.client
+            .update("users", &user_id.to_string(), update_data)
+            .await
+            .map_err(|e| AppError::SupabaseRequest(e.to_string()))?;
+
+        info!(%user_id, "User cookie updated in database");
+        Ok(())
+    }
+
     #[tracing::instrument(skip_all)]
     pub async fn list_users(&self) -> Result<Vec<Account>> {
         info!("Fetching all users from Supabase 'users' table...");
@@ -93,4 +130,103 @@ impl DbClient {
 
         Ok(accounts)
     }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn insert_trade(&self, trade: &Trade) -> Result<()> {
+        let trade_data = serde_json::to_value(trade)?;
+        if self.intercept("insert_trade", "trades", &trade_data) {
+            return Ok(());
+        }
+        let inserted_id = self
+            .client
+            .insert("trades", trade_data)
+            .await
+            .map_err(|e| AppError::SupabaseRequest(e.to_string()))?;
+
+        debug!(?inserted_id, "Trade recorded in ledger");
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn insert_session_report(&self, session_report: &SessionReport) -> Result<()> {
+        let session_report_data = serde_json::to_value(session_report)?;
+        if self.intercept(
+            "insert_session_report",
+            "session_reports",
+            &session_report_data,
+        ) {
+            return Ok(());
+        }
+        let inserted_id = self
+            .client
+            .insert("session_reports", session_report_data)
+            .await
+            .map_err(|e| AppError::SupabaseRequest(e.to_string()))?;
+
+        info!(?inserted_id, "Session report saved");
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn list_trades(&self) -> Result<Vec<Trade>> {
+        let raw_trades_data = self
+            .client
+            .select("trades")
+            .execute()
+            .await
+            .map_err(|e| AppError::SupabaseRequest(e.to_string()))?;
+
+        let trades: Vec<Trade> = raw_trades_data
+            .into_iter()
+            .filter_map(|raw_trade_value| {
+                serde_json::from_value::<Trade>(raw_trade_value)
+                    .inspect_err(
+                        |e| warn!(error = %e, "Failed to deserialize trade. Skipping this entry."),
+                    )
+                    .ok()
+            })
+            .collect();
+
+        info!(count = trades.len(), "Fetched trades from ledger.");
+        Ok(trades)
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn load_crystal_spend(
+        &self,
+        character_id: u64,
+    ) -> Result<Option<EssenceCrystalSpend>> {
+        let raw_spend_data = self
+            .client
+            .select("crystal_budgets")
+            .eq("id", &character_id.to_string())
+            .execute()
+            .await
+            .map_err(|e| AppError::SupabaseRequest(e.to_string()))?;
+
+        Ok(raw_spend_data
+            .into_iter()
+            .next()
+            .map(serde_json::from_value)
+            .transpose()?)
+    }
+
+    #[tracing::instrument(skip(self, spend))]
+    pub async fn save_crystal_spend(
+        &self,
+        character_id: u64,
+        spend: &EssenceCrystalSpend,
+    ) -> Result<()> {
+        let spend_data = serde_json::to_value(spend)?;
+        if self.intercept("save_crystal_spend", "crystal_budgets", &spend_data) {
+            return Ok(());
+        }
+        self.client
+            .upsert("crystal_budgets", &character_id.to_string(), spend_data)
+            .await
+            .map_err(|e| AppError::SupabaseRequest(e.to_string()))?;
+
+        debug!(%character_id, "Essence crystal spend saved");
+        Ok(())
+    }
 }
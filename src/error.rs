@@ -46,8 +46,42 @@ pub enum AppError {
     #[error("User input error: {0}")]
     UserInputError(#[from] requestty::ErrorKind),
 
+    #[error("Session expired for account {0}")]
+    SessionExpired(u64),
+
+    #[error("Two-factor authentication failed: {0}")]
+    TwoFactor(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Travel error: {0}")]
+    Travel(String),
+
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("No recorded response for {0}")]
+    Replay(String),
+
     #[error("Application error: {0}")]
     Application(String),
 }
 
+impl AppError {
+    /// Process exit code for this error, following the BSD `sysexits.h` values.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::UserInputError(_) => 64,
+            Self::Parse(_) | Self::SerdeJson(_) | Self::ParseInt(_) | Self::Regex(_) => 65,
+            Self::NotFound(_) | Self::Replay(_) => 66,
+            Self::Reqwest(_) | Self::SupabaseRequest(_) | Self::Travel(_) => 69,
+            Self::Io(_) | Self::Sqlite(_) => 74,
+            Self::SessionExpired(_) | Self::TwoFactor(_) => 77,
+            Self::Config(_) | Self::SupabaseBuilder(_) => 78,
+            _ => 1,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, AppError>;
@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::debug;
+
+use crate::{
+    health::HealthEvent,
+    models::{CharacterInfo, SkillType, Trade},
+    supervisor::{SupervisorStatus, current_account_id},
+};
+
+/// Events kept for subscribers that fall behind before they are dropped.
+const EVENT_CAPACITY: usize = 1024;
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotEvent {
+    AccountLoaded {
+        account_id: u64,
+        character: String,
+    },
+    SessionExpired {
+        account_id: u64,
+    },
+    TwoFactorRequired {
+        email: String,
+        attempt: u32,
+    },
+    CharacterSwitched {
+        character_id: u64,
+        character: String,
+    },
+    Travelled {
+        location_id: u64,
+        location: String,
+    },
+    Teleported {
+        location_id: u64,
+        location: String,
+        cost: u64,
+    },
+    ActionStarted {
+        character: String,
+        skill: SkillType,
+        item: String,
+    },
+    ActionProgress {
+        character: String,
+        skill: SkillType,
+        item: String,
+        /// Percent of the current item done.
+        progress: f64,
+        quantity: u64,
+        max_quantity: u64,
+        remaining_secs: i64,
+    },
+    ActionCompleted {
+        character: String,
+        skill: SkillType,
+    },
+    LevelUp {
+        character: String,
+        /// `None` for the combat level.
+        skill: Option<SkillType>,
+        level: u64,
+    },
+    GoldChanged {
+        character: String,
+        previous: u64,
+        current: u64,
+    },
+    DungeonRewardsCollected {
+        character: String,
+        gold: u64,
+        experience: u64,
+    },
+    TradeCompleted {
+        trade: Trade,
+    },
+    HealthIntervention {
+        event: HealthEvent,
+    },
+    ParseFailure {
+        parser: String,
+    },
+    ActionFailed {
+        error: String,
+        consecutive_failures: u32,
+    },
+    SupervisorStatusChanged {
+        status: SupervisorStatus,
+        reason: Option<String>,
+    },
+}
+
+impl BotEvent {
+    /// Snake-case name of the variant, as used in the serialized `type` field.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::AccountLoaded { .. } => "account_loaded",
+            Self::SessionExpired { .. } => "session_expired",
+            Self::TwoFactorRequired { .. } => "two_factor_required",
+            Self::CharacterSwitched { .. } => "character_switched",
+            Self::Travelled { .. } => "travelled",
+            Self::Teleported { .. } => "teleported",
+            Self::ActionStarted { .. } => "action_started",
+            Self::ActionProgress { .. } => "action_progress",
+            Self::ActionCompleted { .. } => "action_completed",
+            Self::LevelUp { .. } => "level_up",
+            Self::GoldChanged { .. } => "gold_changed",
+            Self::DungeonRewardsCollected { .. } => "dungeon_rewards_collected",
+            Self::TradeCompleted { .. } => "trade_completed",
+            Self::HealthIntervention { .. } => "health_intervention",
+            Self::ParseFailure { .. } => "parse_failure",
+            Self::ActionFailed { .. } => "action_failed",
+            Self::SupervisorStatusChanged { .. } => "supervisor_status_changed",
+        }
+    }
+
+    /// Events implied by a character info refresh: gold changes and level ups.
+    pub fn from_character_update(previous: &CharacterInfo, current: &CharacterInfo) -> Vec<Self> {
+        // A different id means a switch or the first load, not progress.
+        if previous.id != current.id {
+            return vec![];
+        }
+
+        let mut character_events = Vec::new();
+        if previous.gold != current.gold {
+            character_events.push(Self::GoldChanged {
+                character: current.name.clone(),
+                previous: previous.gold,
+                current: current.gold,
+            });
+        }
+        if current.combat_level > previous.combat_level {
+            character_events.push(Self::LevelUp {
+                character: current.name.clone(),
+                skill: None,
+                level: current.combat_level,
+            });
+        }
+        for (skill_type, level) in &current.skill_level {
+            if previous
+                .skill_level
+                .get(skill_type)
+                .is_some_and(|previous_level| level > previous_level)
+            {
+                character_events.push(Self::LevelUp {
+                    character: current.name.clone(),
+                    skill: Some(skill_type.clone()),
+                    level: *level,
+                });
+            }
+        }
+        character_events
+    }
+}
+
+/// A published event, stamped with when and for which account it happened.
+#[derive(Serialize, Debug, Clone)]
+pub struct EventEnvelope {
+    pub at: DateTime<Utc>,
+    pub account_id: Option<u64>,
+    #[serde(flatten)]
+    pub event: BotEvent,
+}
+
+#[derive(Debug)]
+pub struct EventBus {
+    sender: broadcast::Sender<EventEnvelope>,
+}
+
+pub static EVENT_BUS: Lazy<EventBus> = Lazy::new(|| EventBus {
+    sender: broadcast::channel(EVENT_CAPACITY).0,
+});
+
+impl EventBus {
+    /// Publishes an event for the account of the current supervisor task.
+    pub fn publish(&self, event: BotEvent) {
+        let event_envelope = EventEnvelope {
+            at: Utc::now(),
+            account_id: current_account_id(),
+            event,
+        };
+        debug!(event = ?event_envelope.event, "Event published.");
+        // Publishing without subscribers is fine, the event is simply dropped.
+        self.sender.send(event_envelope).ok();
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<EventEnvelope> {
+        self.sender.subscribe()
+    }
+}
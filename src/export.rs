@@ -0,0 +1,485 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::{
+    client::{ActionSkillApi, IdleMMOClient},
+    error::{AppError, Result},
+    models::SkillType,
+    parser::Parser,
+    utils::write_atomically,
+};
+
+/// Bumped whenever the exported tables change, so consumers can tell layouts apart.
+/// Also stored as the SQLite `user_version`.
+const FORMAT_VERSION: u32 = 2;
+
+const SQLITE_SCHEMA: &str = "
+CREATE TABLE export_info (
+    format_version INTEGER NOT NULL,
+    game_version TEXT,
+    exported_at TEXT NOT NULL,
+    bot_version TEXT NOT NULL
+);
+CREATE TABLE export_failures (
+    kind TEXT NOT NULL,
+    id TEXT NOT NULL,
+    error TEXT NOT NULL
+);
+CREATE TABLE locations (
+    id INTEGER PRIMARY KEY,
+    key TEXT NOT NULL,
+    name TEXT NOT NULL,
+    recommended_level INTEGER NOT NULL
+);
+CREATE TABLE enemies (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    level INTEGER NOT NULL
+);
+CREATE TABLE location_enemies (
+    location_id INTEGER NOT NULL REFERENCES locations (id),
+    enemy_id INTEGER NOT NULL REFERENCES enemies (id),
+    PRIMARY KEY (location_id, enemy_id)
+);
+CREATE TABLE skill_items (
+    id INTEGER PRIMARY KEY,
+    name TEXT,
+    skill_type TEXT NOT NULL,
+    level_required INTEGER NOT NULL,
+    wait_length_ms INTEGER,
+    experience INTEGER
+);
+CREATE TABLE location_skill_items (
+    location_id INTEGER NOT NULL REFERENCES locations (id),
+    skill_item_id INTEGER NOT NULL REFERENCES skill_items (id),
+    PRIMARY KEY (location_id, skill_item_id)
+);
+CREATE TABLE skill_item_requirements (
+    skill_item_id INTEGER NOT NULL REFERENCES skill_items (id),
+    required_item_id INTEGER NOT NULL,
+    required_item_name TEXT,
+    quantity INTEGER,
+    PRIMARY KEY (skill_item_id, required_item_id)
+);
+CREATE TABLE dungeons (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    level_required INTEGER NOT NULL,
+    gold_cost INTEGER NOT NULL,
+    wait_length_ms INTEGER,
+    location_id INTEGER NOT NULL REFERENCES locations (id)
+);
+";
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Sqlite,
+}
+
+impl ExportFormat {
+    /// SQLite for `.sqlite`, `.sqlite3` and `.db` files, JSON otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("sqlite" | "sqlite3" | "db") => Self::Sqlite,
+            _ => Self::Json,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Sqlite => "sqlite",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ExportInfo {
+    pub format_version: u32,
+    /// Build the data was crawled from, when the game page revealed it.
+    pub game_version: Option<String>,
+    pub exported_at: DateTime<Utc>,
+    pub bot_version: String,
+    /// What the crawl could not fetch and the export therefore lacks.
+    pub failures: Vec<ExportFailure>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ExportFailure {
+    /// `location`, `skill` or `dungeon`.
+    pub kind: &'static str,
+    pub id: String,
+    pub error: String,
+}
+
+/// Location details that do not depend on the character. Distances and teleport costs
+/// are left out, they are measured from wherever the character stands.
+#[derive(Serialize, Debug, Clone)]
+pub struct LocationRow {
+    pub id: u64,
+    pub key: String,
+    pub name: String,
+    pub recommended_level: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct EnemyRow {
+    pub id: u64,
+    pub name: String,
+    pub level: u64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LocationEnemyRow {
+    pub location_id: u64,
+    pub enemy_id: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SkillItemRow {
+    pub id: u64,
+    pub name: Option<String>,
+    pub skill_type: SkillType,
+    pub level_required: u64,
+    pub wait_length_ms: Option<u64>,
+    pub experience: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LocationSkillItemRow {
+    pub location_id: u64,
+    pub skill_item_id: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SkillItemRequirementRow {
+    pub skill_item_id: u64,
+    pub required_item_id: u64,
+    pub required_item_name: Option<String>,
+    pub quantity: Option<u64>,
+}
+
+/// Dungeon details without the character's cooldown.
+#[derive(Serialize, Debug, Clone)]
+pub struct DungeonRow {
+    pub id: u64,
+    pub name: String,
+    pub level_required: u64,
+    pub gold_cost: u64,
+    pub wait_length_ms: Option<u64>,
+    pub location_id: u64,
+}
+
+/// Everything the crawl found, as one table per kind of record with rows ordered by
+/// id, so exports of two game versions diff cleanly.
+#[derive(Serialize, Debug, Clone)]
+pub struct GameDataExport {
+    pub export_info: ExportInfo,
+    pub locations: Vec<LocationRow>,
+    pub enemies: Vec<EnemyRow>,
+    pub location_enemies: Vec<LocationEnemyRow>,
+    pub skill_items: Vec<SkillItemRow>,
+    pub location_skill_items: Vec<LocationSkillItemRow>,
+    pub skill_item_requirements: Vec<SkillItemRequirementRow>,
+    pub dungeons: Vec<DungeonRow>,
+}
+
+impl GameDataExport {
+    /// Crawls every location, the items of every startable skill with their
+    /// requirements, and every dungeon, straight from the game rather than any cache.
+    /// Locations, skills and dungeons whose details fail are left out and listed in
+    /// `ExportInfo::failures`.
+    #[tracing::instrument(skip_all)]
+    pub async fn crawl(client: &mut IdleMMOClient) -> Result<Self> {
+        let mut export_info = ExportInfo {
+            format_version: FORMAT_VERSION,
+            game_version: Parser::GameVersion.get_value(&client.cache.html).ok(),
+            exported_at: Utc::now(),
+            bot_version: env!("CARGO_PKG_VERSION").to_string(),
+            failures: vec![],
+        };
+
+        let location_fetch = client.fetch_all_locations().await?;
+        export_info
+            .failures
+            .extend(
+                location_fetch
+                    .failures
+                    .into_iter()
+                    .map(|location_failure| ExportFailure {
+                        kind: "location",
+                        id: location_failure.location_id.to_string(),
+                        error: location_failure.error,
+                    }),
+            );
+        let world_locations = location_fetch.locations;
+        let mut locations = BTreeMap::new();
+        let mut enemies = BTreeMap::new();
+        let mut location_enemies = BTreeSet::new();
+        let mut skill_items = BTreeMap::new();
+        let mut location_skill_items = BTreeSet::new();
+        let mut skill_item_requirements = BTreeMap::new();
+        for world_location in &world_locations {
+            locations.insert(
+                world_location.id,
+                LocationRow {
+                    id: world_location.id,
+                    key: world_location.key.clone(),
+                    name: world_location.name.clone(),
+                    recommended_level: world_location.recommended_level,
+                },
+            );
+            for enemy in &world_location.enemies {
+                enemies.entry(enemy.id).or_insert_with(|| EnemyRow {
+                    id: enemy.id,
+                    name: enemy.name.clone(),
+                    level: enemy.level,
+                });
+                location_enemies.insert(LocationEnemyRow {
+                    location_id: world_location.id,
+                    enemy_id: enemy.id,
+                });
+            }
+            for skill_item in &world_location.skill_items {
+                skill_items
+                    .entry(skill_item.id)
+                    .or_insert_with(|| skill_item.clone());
+                location_skill_items.insert(LocationSkillItemRow {
+                    location_id: world_location.id,
+                    skill_item_id: skill_item.id,
+                });
+            }
+        }
+
+        // Skill pages describe items in more detail than locations do, and list the
+        // crafted items that are not found in any location.
+        for skill_type in enum_iterator::all::<SkillType>().filter(SkillType::is_startable) {
+            match client.get_skill_data(skill_type.clone()).await {
+                Ok(skill_data) => {
+                    for skill_item in skill_data.items {
+                        skill_items.insert(skill_item.id, skill_item);
+                    }
+                }
+                Err(e) => {
+                    warn!(%skill_type, error = %e, "Failed to fetch skill data. Skipping it.");
+                    export_info.failures.push(ExportFailure {
+                        kind: "skill",
+                        id: skill_type.to_string(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+        for skill_item in skill_items.values() {
+            for required_item in &skill_item.requirements {
+                skill_item_requirements.insert(
+                    (skill_item.id, required_item.id),
+                    SkillItemRequirementRow {
+                        skill_item_id: skill_item.id,
+                        required_item_id: required_item.id,
+                        required_item_name: required_item.name.clone(),
+                        quantity: required_item.quantity_requirement,
+                    },
+                );
+            }
+        }
+
+        let dungeon_fetch = client.fetch_dungeon_details(&world_locations).await?;
+        export_info
+            .failures
+            .extend(
+                dungeon_fetch
+                    .failures
+                    .into_iter()
+                    .map(|dungeon_failure| ExportFailure {
+                        kind: "dungeon",
+                        id: dungeon_failure.dungeon_id.to_string(),
+                        error: dungeon_failure.error,
+                    }),
+            );
+        let mut dungeons: Vec<DungeonRow> = dungeon_fetch
+            .dungeons
+            .into_iter()
+            .map(|dungeon| DungeonRow {
+                id: dungeon.id,
+                name: dungeon.name,
+                level_required: dungeon.level_required,
+                gold_cost: dungeon.gold_cost,
+                wait_length_ms: dungeon.wait_length_ms,
+                location_id: dungeon.location_id,
+            })
+            .collect();
+        dungeons.sort_by_key(|dungeon| dungeon.id);
+        dungeons.dedup_by_key(|dungeon| dungeon.id);
+
+        let game_data_export = Self {
+            export_info,
+            locations: locations.into_values().collect(),
+            enemies: enemies.into_values().collect(),
+            location_enemies: location_enemies.into_iter().collect(),
+            skill_items: skill_items
+                .into_values()
+                .map(|skill_item| SkillItemRow {
+                    id: skill_item.id,
+                    name: skill_item.name,
+                    skill_type: skill_item.skill_type,
+                    level_required: skill_item.level_required,
+                    wait_length_ms: skill_item.wait_length_ms,
+                    experience: skill_item.experience,
+                })
+                .collect(),
+            location_skill_items: location_skill_items.into_iter().collect(),
+            skill_item_requirements: skill_item_requirements.into_values().collect(),
+            dungeons,
+        };
+        info!(
+            locations = game_data_export.locations.len(),
+            enemies = game_data_export.enemies.len(),
+            skill_items = game_data_export.skill_items.len(),
+            dungeons = game_data_export.dungeons.len(),
+            failures = game_data_export.export_info.failures.len(),
+            "Game data crawled."
+        );
+        Ok(game_data_export)
+    }
+
+    /// `game-data-<game version>-<timestamp>.<extension>`, in the current directory.
+    pub fn default_path(&self, export_format: ExportFormat) -> PathBuf {
+        let game_version = self
+            .export_info
+            .game_version
+            .as_deref()
+            .unwrap_or("unknown");
+        let timestamp = self.export_info.exported_at.format("%Y%m%dT%H%M%SZ");
+        PathBuf::from(format!(
+            "game-data-{game_version}-{timestamp}.{}",
+            export_format.extension()
+        ))
+    }
+
+    /// Writes the export, replacing `path` only once the new file is complete.
+    pub async fn write(&self, path: &Path, export_format: ExportFormat) -> Result<()> {
+        match export_format {
+            ExportFormat::Json => {
+                write_atomically(path, &serde_json::to_vec_pretty(self)?).await?;
+            }
+            ExportFormat::Sqlite => {
+                let game_data_export = self.clone();
+                let path = path.to_path_buf();
+                tokio::task::spawn_blocking(move || game_data_export.write_sqlite(&path))
+                    .await
+                    .map_err(anyhow::Error::from)??;
+            }
+        }
+        info!(path = %path.display(), ?export_format, "Game data exported.");
+        Ok(())
+    }
+
+    fn write_sqlite(&self, path: &Path) -> Result<()> {
+        let temporary_path = path.with_extension("tmp");
+        if temporary_path.exists() {
+            std::fs::remove_file(&temporary_path)?;
+        }
+        let mut connection = Connection::open(&temporary_path)?;
+        connection.pragma_update(None, "user_version", FORMAT_VERSION)?;
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(SQLITE_SCHEMA)?;
+
+        transaction.execute(
+            "INSERT INTO export_info VALUES (?1, ?2, ?3, ?4)",
+            params![
+                self.export_info.format_version,
+                self.export_info.game_version,
+                self.export_info.exported_at.to_rfc3339(),
+                self.export_info.bot_version,
+            ],
+        )?;
+        {
+            let mut insert_failure =
+                transaction.prepare("INSERT INTO export_failures VALUES (?1, ?2, ?3)")?;
+            for export_failure in &self.export_info.failures {
+                insert_failure.execute(params![
+                    export_failure.kind,
+                    export_failure.id,
+                    export_failure.error,
+                ])?;
+            }
+            let mut insert_location =
+                transaction.prepare("INSERT INTO locations VALUES (?1, ?2, ?3, ?4)")?;
+            for location in &self.locations {
+                insert_location.execute(params![
+                    location.id,
+                    location.key,
+                    location.name,
+                    location.recommended_level,
+                ])?;
+            }
+            let mut insert_enemy =
+                transaction.prepare("INSERT INTO enemies VALUES (?1, ?2, ?3)")?;
+            for enemy in &self.enemies {
+                insert_enemy.execute(params![enemy.id, enemy.name, enemy.level])?;
+            }
+            let mut insert_location_enemy =
+                transaction.prepare("INSERT INTO location_enemies VALUES (?1, ?2)")?;
+            for location_enemy in &self.location_enemies {
+                insert_location_enemy
+                    .execute(params![location_enemy.location_id, location_enemy.enemy_id])?;
+            }
+            let mut insert_skill_item =
+                transaction.prepare("INSERT INTO skill_items VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+            for skill_item in &self.skill_items {
+                insert_skill_item.execute(params![
+                    skill_item.id,
+                    skill_item.name,
+                    skill_item.skill_type.to_string(),
+                    skill_item.level_required,
+                    skill_item.wait_length_ms,
+                    skill_item.experience,
+                ])?;
+            }
+            let mut insert_location_skill_item =
+                transaction.prepare("INSERT INTO location_skill_items VALUES (?1, ?2)")?;
+            for location_skill_item in &self.location_skill_items {
+                insert_location_skill_item.execute(params![
+                    location_skill_item.location_id,
+                    location_skill_item.skill_item_id,
+                ])?;
+            }
+            let mut insert_requirement = transaction
+                .prepare("INSERT INTO skill_item_requirements VALUES (?1, ?2, ?3, ?4)")?;
+            for requirement in &self.skill_item_requirements {
+                insert_requirement.execute(params![
+                    requirement.skill_item_id,
+                    requirement.required_item_id,
+                    requirement.required_item_name,
+                    requirement.quantity,
+                ])?;
+            }
+            let mut insert_dungeon =
+                transaction.prepare("INSERT INTO dungeons VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+            for dungeon in &self.dungeons {
+                insert_dungeon.execute(params![
+                    dungeon.id,
+                    dungeon.name,
+                    dungeon.level_required,
+                    dungeon.gold_cost,
+                    dungeon.wait_length_ms,
+                    dungeon.location_id,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        connection.close().map_err(|(_, e)| AppError::Sqlite(e))?;
+        std::fs::rename(&temporary_path, path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,461 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64_STD};
+use chrono::{DateTime, Utc};
+use once_cell::sync::{Lazy, OnceCell};
+use reqwest::{
+    Request, StatusCode, Version,
+    header::{self, HeaderMap},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::{error::Result, lazy_regex, utils::write_atomically};
+
+/// Entries per file before the recorder starts a new one, so rewrites stay cheap.
+const ENTRIES_PER_FILE: usize = 500;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Form and query fields whose value is always redacted.
+pub const SENSITIVE_FIELDS: [&str; 6] = [
+    "_token",
+    "password",
+    "email",
+    "api_token",
+    "cookie_str",
+    "code",
+];
+
+/// Headers whose value is always redacted.
+const SENSITIVE_HEADERS: [&str; 3] = ["x-csrf-token", "x-xsrf-token", "x-api-token"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Har {
+    pub log: HarLog,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HarLog {
+    pub version: String,
+    pub creator: HarCreator,
+    pub entries: Vec<HarEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HarCreator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntry {
+    pub started_date_time: DateTime<Utc>,
+    /// Total time in milliseconds.
+    pub time: f64,
+    pub request: HarRequest,
+    pub response: HarResponse,
+    #[serde(default)]
+    pub cache: BTreeMap<String, Value>,
+    pub timings: HarTimings,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    pub headers: Vec<HarNameValue>,
+    pub query_string: Vec<HarNameValue>,
+    /// Always empty, cookies are only kept as redacted headers.
+    #[serde(default)]
+    pub cookies: Vec<HarNameValue>,
+    pub headers_size: i64,
+    pub body_size: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<HarPostData>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub http_version: String,
+    pub headers: Vec<HarNameValue>,
+    #[serde(default)]
+    pub cookies: Vec<HarNameValue>,
+    pub content: HarContent,
+    #[serde(rename = "redirectURL", default)]
+    pub redirect_url: String,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HarNameValue {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPostData {
+    pub mime_type: String,
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HarContent {
+    pub size: i64,
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// `base64` for bodies that are not valid UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HarTimings {
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+}
+
+impl Har {
+    fn new() -> Self {
+        Self {
+            log: HarLog {
+                version: "1.2".to_string(),
+                creator: HarCreator {
+                    name: env!("CARGO_PKG_NAME").to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                },
+                entries: vec![],
+            },
+        }
+    }
+}
+
+impl HarEntry {
+    /// `wait` is the time until the response headers arrived, `total` includes reading the body.
+    pub fn new(
+        started_at: DateTime<Utc>,
+        wait: Duration,
+        total: Duration,
+        request: HarRequest,
+        response: HarResponse,
+    ) -> Self {
+        Self {
+            started_date_time: started_at,
+            time: total.as_secs_f64() * 1000.0,
+            request,
+            response,
+            cache: BTreeMap::new(),
+            timings: HarTimings {
+                send: 0.0,
+                wait: wait.as_secs_f64() * 1000.0,
+                receive: total.saturating_sub(wait).as_secs_f64() * 1000.0,
+            },
+        }
+    }
+}
+
+impl HarRequest {
+    pub fn from_request(request: &Request) -> Self {
+        let body = request.body().and_then(|body| body.as_bytes());
+        Self {
+            method: request.method().to_string(),
+            url: redact_text(request.url().as_str()),
+            http_version: format!("{:?}", request.version()),
+            headers: redact_headers(request.headers()),
+            query_string: request
+                .url()
+                .query_pairs()
+                .map(|(name, value)| HarNameValue {
+                    value: redact_field(&name, &value),
+                    name: name.into_owned(),
+                })
+                .collect(),
+            cookies: vec![],
+            headers_size: -1,
+            body_size: body.map_or(0, |body| body.len() as i64),
+            post_data: body.map(|body| HarPostData {
+                mime_type: content_type(request.headers()),
+                text: redact_text(&String::from_utf8_lossy(body)),
+            }),
+        }
+    }
+}
+
+impl HarResponse {
+    pub fn new(status: StatusCode, version: Version, headers: &HeaderMap, body: &[u8]) -> Self {
+        let (text, encoding) = match std::str::from_utf8(body) {
+            Ok(body_text) => (redact_text(body_text), None),
+            Err(_) => (BASE64_STD.encode(body), Some("base64".to_string())),
+        };
+        Self {
+            status: status.as_u16(),
+            status_text: status.canonical_reason().unwrap_or_default().to_string(),
+            http_version: format!("{version:?}"),
+            headers: redact_headers(headers),
+            cookies: vec![],
+            content: HarContent {
+                size: body.len() as i64,
+                mime_type: content_type(headers),
+                text: Some(text),
+                encoding,
+            },
+            redirect_url: headers
+                .get(header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .map(redact_text)
+                .unwrap_or_default(),
+            headers_size: -1,
+            body_size: body.len() as i64,
+        }
+    }
+}
+
+fn content_type(headers: &HeaderMap) -> String {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn redact_headers(headers: &HeaderMap) -> Vec<HarNameValue> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = value.to_str().unwrap_or_default();
+            let redacted_value = if name == header::AUTHORIZATION {
+                match value.split_once(' ') {
+                    Some((scheme, _)) => format!("{scheme} {REDACTED}"),
+                    None => REDACTED.to_string(),
+                }
+            } else if name == header::COOKIE {
+                value
+                    .split("; ")
+                    .map(redact_cookie)
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            } else if name == header::SET_COOKIE {
+                // Keep the attributes, they explain expiry problems.
+                match value.split_once(';') {
+                    Some((cookie, attributes)) => {
+                        format!("{};{attributes}", redact_cookie(cookie))
+                    }
+                    None => redact_cookie(value),
+                }
+            } else if SENSITIVE_HEADERS.contains(&name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                redact_text(value)
+            };
+            HarNameValue {
+                name: name.to_string(),
+                value: redacted_value,
+            }
+        })
+        .collect()
+}
+
+fn redact_cookie(cookie: &str) -> String {
+    match cookie.split_once('=') {
+        Some((cookie_name, _)) => format!("{cookie_name}={REDACTED}"),
+        None => REDACTED.to_string(),
+    }
+}
+
+fn redact_field(name: &str, value: &str) -> String {
+    if SENSITIVE_FIELDS.contains(&name) {
+        REDACTED.to_string()
+    } else {
+        redact_text(value)
+    }
+}
+
+/// Redacts tokens, passwords and emails from a URL, form, JSON or HTML body.
+pub fn redact_text(text: &str) -> String {
+    let json_fields = lazy_regex!(
+        r#"("(?:_token|password|email|api_token|cookie_str|csrf_token)"\s*:\s*)"(?:[^"\\]|\\.)*""#
+    );
+    let form_fields =
+        lazy_regex!(r"(^|[?&])(_token|password|email|api_token|cookie_str|code)=[^&#\s]*");
+    let meta_tokens = lazy_regex!(r#"(name="(?:csrf-token|api-token)"\s*content=")[^"]*"#);
+    let hidden_tokens = lazy_regex!(r#"(name="_token"\s+value=")[^"]*"#);
+    let bearer_tokens = lazy_regex!(r"(Bearer\s+)[A-Za-z0-9._~+/=|-]+");
+    let emails = lazy_regex!(r"[A-Za-z0-9._%+-]+(?:@|%40)[A-Za-z0-9.-]+\.[A-Za-z]{2,}");
+
+    let text = json_fields.replace_all(text, format!(r#"$1"{REDACTED}""#));
+    let text = form_fields.replace_all(&text, format!("${{1}}${{2}}={REDACTED}"));
+    let text = meta_tokens.replace_all(&text, format!("${{1}}{REDACTED}"));
+    let text = hidden_tokens.replace_all(&text, format!("${{1}}{REDACTED}"));
+    let text = bearer_tokens.replace_all(&text, format!("${{1}}{REDACTED}"));
+    emails.replace_all(&text, "[REDACTED_EMAIL]").into_owned()
+}
+
+struct HarFile {
+    path: PathBuf,
+    har: Har,
+}
+
+impl HarFile {
+    fn new(directory: &Path, account: &str) -> Self {
+        Self {
+            path: directory.join(format!(
+                "account-{account}-{}.har",
+                Utc::now().format("%Y%m%d-%H%M%S%.3f")
+            )),
+            har: Har::new(),
+        }
+    }
+}
+
+/// Writes every request sent to the game to one HAR file per account, once enabled.
+#[derive(Default)]
+pub struct HarRecorder {
+    directory: OnceCell<PathBuf>,
+    files: Mutex<HashMap<String, HarFile>>,
+}
+
+pub static HAR_RECORDER: Lazy<HarRecorder> = Lazy::new(HarRecorder::default);
+
+impl HarRecorder {
+    pub fn enable(&self, directory: &Path) -> Result<()> {
+        std::fs::create_dir_all(directory)?;
+        if self.directory.set(directory.to_path_buf()).is_ok() {
+            info!(directory = %directory.display(), "Recording HTTP traffic.");
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.directory.get().is_some()
+    }
+
+    pub async fn record(&self, account: &str, har_entry: HarEntry) {
+        let Some(directory) = self.directory.get() else {
+            return;
+        };
+
+        let mut files = self.files.lock().await;
+        let har_file = files
+            .entry(account.to_string())
+            .or_insert_with(|| HarFile::new(directory, account));
+        if har_file.har.log.entries.len() >= ENTRIES_PER_FILE {
+            *har_file = HarFile::new(directory, account);
+        }
+        har_file.har.log.entries.push(har_entry);
+
+        let write_result = match serde_json::to_vec_pretty(&har_file.har) {
+            Ok(har_json) => write_atomically(&har_file.path, &har_json).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = write_result {
+            warn!(path = %har_file.path.display(), error = %e, "Failed to write HAR file.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    fn redacted_header(name: header::HeaderName, value: &str) -> String {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        redact_headers(&headers).remove(0).value
+    }
+
+    #[test]
+    fn redacts_form_fields() {
+        assert_eq!(
+            redact_text("_token=abc123&email=me%40example.com&password=hunter2&remember=on"),
+            "_token=[REDACTED]&email=[REDACTED]&password=[REDACTED]&remember=on"
+        );
+    }
+
+    #[test]
+    fn redacts_json_fields() {
+        assert_eq!(
+            redact_text(r#"{"_token": "abc", "password": "hun\"ter2", "character_id": 7}"#),
+            r#"{"_token": "[REDACTED]", "password": "[REDACTED]", "character_id": 7}"#
+        );
+    }
+
+    #[test]
+    fn redacts_html_tokens() {
+        assert_eq!(
+            redact_text(
+                r#"<meta name="csrf-token" content="abc"><input name="_token" value="def">"#
+            ),
+            r#"<meta name="csrf-token" content="[REDACTED]"><input name="_token" value="[REDACTED]">"#
+        );
+    }
+
+    #[test]
+    fn redacts_bearer_tokens_and_emails() {
+        assert_eq!(
+            redact_text("Bearer 12|abc.DEF-ghi sent by player@example.com"),
+            "Bearer [REDACTED] sent by [REDACTED_EMAIL]"
+        );
+    }
+
+    #[test]
+    fn redacts_authorization_but_keeps_the_scheme() {
+        assert_eq!(
+            redacted_header(header::AUTHORIZATION, "Bearer 12|secret"),
+            "Bearer [REDACTED]"
+        );
+        assert_eq!(redacted_header(header::AUTHORIZATION, "secret"), REDACTED);
+    }
+
+    #[test]
+    fn redacts_cookie_values_but_keeps_names() {
+        assert_eq!(
+            redacted_header(header::COOKIE, "XSRF-TOKEN=abc; idlemmo_session=def"),
+            "XSRF-TOKEN=[REDACTED]; idlemmo_session=[REDACTED]"
+        );
+    }
+
+    #[test]
+    fn redacts_set_cookie_values_but_keeps_attributes() {
+        assert_eq!(
+            redacted_header(
+                header::SET_COOKIE,
+                "idlemmo_session=def; expires=Sun, 18 Oct 2026 12:00:00 GMT; path=/; httponly"
+            ),
+            "idlemmo_session=[REDACTED]; expires=Sun, 18 Oct 2026 12:00:00 GMT; path=/; httponly"
+        );
+    }
+
+    #[test]
+    fn redacts_sensitive_headers_and_text_in_others() {
+        assert_eq!(
+            redacted_header(header::HeaderName::from_static("x-csrf-token"), "abc"),
+            REDACTED
+        );
+        assert_eq!(
+            redacted_header(
+                header::REFERER,
+                "https://web.idle-mmo.com/?email=me@example.com"
+            ),
+            "https://web.idle-mmo.com/?email=[REDACTED]"
+        );
+    }
+}
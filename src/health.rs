@@ -0,0 +1,207 @@
+use std::{collections::VecDeque, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    client::{IdleMMOClient, InventoryApi},
+    error::{AppError, Result},
+    events::{BotEvent, EVENT_BUS},
+    models::CharacterInfo,
+};
+
+/// Health events kept for inspection, oldest dropped first.
+const RECENT_HEALTH_EVENTS: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct HealthPolicy {
+    /// Pause once health falls below this fraction of `max_health`.
+    pub pause_below: f64,
+    /// Restart once health is back to this fraction of `max_health`.
+    pub resume_at: f64,
+    pub use_consumables: bool,
+    pub regeneration_poll_interval: Duration,
+    /// Give up waiting once regeneration takes longer than this.
+    pub max_regeneration_time: Duration,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self {
+            pause_below: 0.4,
+            resume_at: 0.9,
+            use_consumables: true,
+            regeneration_poll_interval: Duration::from_secs(60),
+            max_regeneration_time: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum HealthIntervention {
+    Paused,
+    ConsumableUsed { item_name: String, heal_amount: u64 },
+    Regenerating { wait_secs: u64 },
+    Resumed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HealthEvent {
+    pub at: DateTime<Utc>,
+    pub character_id: u64,
+    pub health: u64,
+    pub max_health: u64,
+    pub intervention: HealthIntervention,
+}
+
+#[derive(Debug, Default)]
+pub struct HealthManager {
+    pub policy: HealthPolicy,
+    pub events: VecDeque<HealthEvent>,
+    /// When the character started regenerating, while it is.
+    regenerating_since: Option<DateTime<Utc>>,
+}
+
+impl HealthManager {
+    pub fn new(policy: HealthPolicy) -> Self {
+        Self {
+            policy,
+            events: VecDeque::new(),
+            regenerating_since: None,
+        }
+    }
+
+    fn health_ratio(character_info: &CharacterInfo) -> f64 {
+        if character_info.max_health == 0 {
+            return 1.0;
+        }
+        character_info.health as f64 / character_info.max_health as f64
+    }
+
+    pub fn needs_recovery(&self, character_info: &CharacterInfo) -> bool {
+        Self::health_ratio(character_info) < self.policy.pause_below
+    }
+
+    fn is_recovered(&self, character_info: &CharacterInfo) -> bool {
+        Self::health_ratio(character_info) >= self.policy.resume_at
+    }
+
+    fn record(&mut self, character_info: &CharacterInfo, intervention: HealthIntervention) {
+        info!(
+            character_id = character_info.id,
+            health = character_info.health,
+            max_health = character_info.max_health,
+            ?intervention,
+            "Health intervention."
+        );
+        let health_event = HealthEvent {
+            at: Utc::now(),
+            character_id: character_info.id,
+            health: character_info.health,
+            max_health: character_info.max_health,
+            intervention,
+        };
+        EVENT_BUS.publish(BotEvent::HealthIntervention {
+            event: health_event.clone(),
+        });
+        if self.events.len() == RECENT_HEALTH_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(health_event);
+    }
+
+    /// Checks whether the character is healthy enough to fight, using consumables
+    /// first. Returns how long to wait before checking again while it regenerates,
+    /// so the caller keeps handling commands in the meantime.
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_healthy(&mut self, client: &mut IdleMMOClient) -> Result<Option<Duration>> {
+        client.refresh_character_info().await?;
+
+        if let Some(regenerating_since) = self.regenerating_since {
+            if self.is_recovered(&client.cache.character_info) {
+                self.regenerating_since = None;
+                let character_info = &client.cache.character_info;
+                self.record(character_info, HealthIntervention::Resumed);
+                return Ok(None);
+            }
+            let regeneration_time = (Utc::now() - regenerating_since)
+                .to_std()
+                .unwrap_or_default();
+            if regeneration_time >= self.policy.max_regeneration_time {
+                self.regenerating_since = None;
+                let character_info = &client.cache.character_info;
+                return Err(AppError::Application(format!(
+                    "Health still at {}/{} after regenerating for {}s",
+                    character_info.health,
+                    character_info.max_health,
+                    regeneration_time.as_secs()
+                )));
+            }
+            return Ok(Some(
+                self.wait_for_regeneration(&client.cache.character_info),
+            ));
+        }
+
+        if !self.needs_recovery(&client.cache.character_info) {
+            return Ok(None);
+        }
+
+        let character_info = &client.cache.character_info;
+        self.record(character_info, HealthIntervention::Paused);
+
+        if self.policy.use_consumables {
+            self.use_consumables(client).await?;
+        }
+        if self.is_recovered(&client.cache.character_info) {
+            let character_info = &client.cache.character_info;
+            self.record(character_info, HealthIntervention::Resumed);
+            return Ok(None);
+        }
+
+        self.regenerating_since = Some(Utc::now());
+        Ok(Some(
+            self.wait_for_regeneration(&client.cache.character_info),
+        ))
+    }
+
+    fn wait_for_regeneration(&mut self, character_info: &CharacterInfo) -> Duration {
+        let wait_secs = self.policy.regeneration_poll_interval.as_secs();
+        self.record(
+            character_info,
+            HealthIntervention::Regenerating { wait_secs },
+        );
+        self.policy.regeneration_poll_interval
+    }
+
+    async fn use_consumables(&mut self, client: &mut IdleMMOClient) -> Result<()> {
+        let mut healing_items: Vec<_> = client
+            .get_inventory()
+            .await?
+            .into_iter()
+            .filter(|item| item.is_healing())
+            .collect();
+        healing_items.sort_by_key(|item| item.heal_amount);
+
+        for mut healing_item in healing_items {
+            while healing_item.quantity > 0 && !self.is_recovered(&client.cache.character_info) {
+                if let Err(e) = client.use_item(&healing_item).await {
+                    warn!(error = %e, item = %healing_item.name, "Failed to use healing item.");
+                    break;
+                }
+                healing_item.quantity -= 1;
+                client.refresh_character_info().await?;
+
+                let character_info = &client.cache.character_info;
+                self.record(
+                    character_info,
+                    HealthIntervention::ConsumableUsed {
+                        item_name: healing_item.name.clone(),
+                        heal_amount: healing_item.heal_amount.unwrap_or_default(),
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+}
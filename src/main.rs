@@ -1,57 +1,98 @@
 #![allow(dead_code, unused)]
+mod api;
+mod cli;
 mod client;
 mod config;
+mod control;
+mod crystals;
+mod daemon;
 mod db;
 mod error;
+mod events;
+mod export;
+mod har;
+mod health;
+mod metrics;
 mod models;
+mod notifier;
+mod output;
 mod parser;
+mod profile;
+mod replay;
+mod report;
+mod scheduler;
+mod supervisor;
+mod transport;
+mod travel;
+mod tui;
+mod two_factor;
 
 mod utils;
+mod world_cache;
 
+use std::{process::ExitCode, time::Duration};
+
+use clap::Parser as _;
 use requestty::{Answers, Question, question::Choice::DefaultSeparator};
-use tracing::{info, warn};
+use tracing::{error, info, warn};
 use tracing_subscriber::{EnvFilter, fmt::Subscriber};
 
 use crate::{
+    cli::Cli,
     client::{AccountManagement, ActionSkillApi, CharacterApi, IdleMMOClient, LocationApi},
     error::Result,
+    har::HAR_RECORDER,
     models::SkillConfig,
+    replay::REPLAY,
+    tui::LogBuffer,
+    world_cache::WORLD_CACHE,
 };
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let log_buffer = cli.uses_dashboard().then(LogBuffer::default);
+
     let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    Subscriber::builder()
+    let subscriber_builder = Subscriber::builder()
         .with_env_filter(env_filter)
         .with_target(false)
         .without_time()
-        .compact()
-        .init();
+        .compact();
+    match &log_buffer {
+        Some(log_buffer) => subscriber_builder
+            .with_ansi(false)
+            .with_writer(log_buffer.clone())
+            .init(),
+        None => subscriber_builder.with_writer(std::io::stderr).init(),
+    }
 
-    run().await
+    match run(cli, log_buffer).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!(error = %e, "Command failed.");
+            ExitCode::from(e.exit_code())
+        }
+    }
 }
 
-#[allow(unreachable_code)]
-async fn run() -> Result<()> {
+async fn run(cli: Cli, log_buffer: Option<LogBuffer>) -> Result<()> {
+    if let Some(har_directory) = &cli.record_har {
+        HAR_RECORDER.enable(har_directory)?;
+    }
+    if let Some(recording_path) = &cli.replay {
+        REPLAY.load(recording_path)?;
+    }
+    if let Some(world_cache_path) = &cli.world_cache {
+        let ttl = Duration::from_secs(cli.world_cache_ttl_hours * 60 * 60);
+        WORLD_CACHE.enable(world_cache_path, ttl)?;
+    }
     let mut client = IdleMMOClient::new()?;
+    client.set_dry_run(cli.dry_run);
 
-    let accounts = client.get_account().await?;
-    let account = fastrand::choice(accounts).unwrap();
-
-    client.load_account(account).await?;
-    dbg!(client.get_locations(false).await?);
-
-    // dbg!(&client.cache.character_info);
-    //
-    // client
-    //     .start_skill(dbg!(SkillConfig {
-    //         skill_type: models::SkillType::Mining,
-    //         ..Default::default()
-    //     }))
-    //     .await?;
-    // dbg!(&client.cache.character_info);
-
-    return Ok(());
+    if cli.command.is_some() {
+        return cli::execute(&mut client, cli, log_buffer).await;
+    }
 
     eprintln!();
     loop {
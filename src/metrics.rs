@@ -0,0 +1,254 @@
+use std::net::SocketAddr;
+
+use axum::{Router, http::header, response::IntoResponse, routing::get};
+use once_cell::sync::Lazy;
+use prometheus::{Encoder, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
+use tracing::{info, warn};
+
+use crate::{
+    error::{AppError, Result},
+    models::{CharacterInfo, Metrics, SkillType},
+    parser::Parser,
+    supervisor::current_account_id,
+};
+
+/// Label used for calls made outside a supervisor, e.g. by one-shot CLI commands.
+const NO_ACCOUNT: &str = "none";
+
+pub fn account_label() -> String {
+    current_account_id()
+        .map(|account_id| account_id.to_string())
+        .unwrap_or_else(|| NO_ACCOUNT.to_string())
+}
+
+/// Replaces ids and hashes in a URL path so endpoints group into a bounded label set.
+pub fn endpoint_label(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            if segment.chars().any(|c| c.is_ascii_digit()) {
+                ":id"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub struct BotMetrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    retries: IntCounterVec,
+    parse_failures: IntCounterVec,
+    actions_started: IntCounterVec,
+    actions_completed: IntCounterVec,
+    session_expired: IntCounterVec,
+    skill_experience: IntGaugeVec,
+    skill_items_gathered: IntGaugeVec,
+    skill_level: IntGaugeVec,
+    gold: IntGaugeVec,
+    tokens: IntGaugeVec,
+    shards: IntGaugeVec,
+    health: IntGaugeVec,
+    max_health: IntGaugeVec,
+}
+
+fn counter(registry: &Registry, name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    let counter_vec = IntCounterVec::new(Opts::new(name, help), labels).unwrap();
+    registry.register(Box::new(counter_vec.clone())).unwrap();
+    counter_vec
+}
+
+fn gauge(registry: &Registry, name: &str, help: &str, labels: &[&str]) -> IntGaugeVec {
+    let gauge_vec = IntGaugeVec::new(Opts::new(name, help), labels).unwrap();
+    registry.register(Box::new(gauge_vec.clone())).unwrap();
+    gauge_vec
+}
+
+pub static METRICS: Lazy<BotMetrics> = Lazy::new(BotMetrics::new);
+
+impl BotMetrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("idlemmo".to_string()), None).unwrap();
+        let character_labels = ["account", "character"];
+        let skill_labels = ["account", "character", "skill"];
+        Self {
+            http_requests: counter(
+                &registry,
+                "http_requests_total",
+                "Requests sent to the game, by endpoint and status.",
+                &["account", "endpoint", "status"],
+            ),
+            retries: counter(
+                &registry,
+                "retries_total",
+                "Operations attempted again after a failure.",
+                &["account", "operation"],
+            ),
+            parse_failures: counter(
+                &registry,
+                "parse_failures_total",
+                "Values that could not be extracted from a game page.",
+                &["account", "parser"],
+            ),
+            actions_started: counter(
+                &registry,
+                "actions_started_total",
+                "Skill actions and dungeons started.",
+                &skill_labels,
+            ),
+            actions_completed: counter(
+                &registry,
+                "actions_completed_total",
+                "Skill actions and dungeons seen to finish.",
+                &skill_labels,
+            ),
+            session_expired: counter(
+                &registry,
+                "session_expired_total",
+                "Stored sessions found to be expired.",
+                &["account"],
+            ),
+            skill_experience: gauge(
+                &registry,
+                "skill_experience",
+                "Total experience gained in a skill, from the skill metrics.",
+                &skill_labels,
+            ),
+            skill_items_gathered: gauge(
+                &registry,
+                "skill_items_gathered",
+                "Total items gathered in a skill, from the skill metrics.",
+                &skill_labels,
+            ),
+            skill_level: gauge(&registry, "skill_level", "Skill level.", &skill_labels),
+            gold: gauge(&registry, "gold", "Gold held.", &character_labels),
+            tokens: gauge(&registry, "tokens", "Tokens held.", &character_labels),
+            shards: gauge(&registry, "shards", "Shards held.", &character_labels),
+            health: gauge(&registry, "health", "Current health.", &character_labels),
+            max_health: gauge(
+                &registry,
+                "max_health",
+                "Maximum health.",
+                &character_labels,
+            ),
+            registry,
+        }
+    }
+
+    pub fn record_request(&self, endpoint: &str, status: &str) {
+        self.http_requests
+            .with_label_values(&[&account_label(), endpoint, status])
+            .inc();
+    }
+
+    pub fn record_retry(&self, operation: &str) {
+        self.retries
+            .with_label_values(&[&account_label(), operation])
+            .inc();
+    }
+
+    pub fn record_parse_failure(&self, parser: &Parser) {
+        self.parse_failures
+            .with_label_values(&[&account_label(), &format!("{parser:?}")])
+            .inc();
+    }
+
+    pub fn record_action_started(&self, character_name: &str, skill_type: &SkillType) {
+        self.actions_started
+            .with_label_values(&[&account_label(), character_name, &skill_type.to_string()])
+            .inc();
+    }
+
+    pub fn record_action_completed(&self, character_name: &str, skill_type: &SkillType) {
+        self.actions_completed
+            .with_label_values(&[&account_label(), character_name, &skill_type.to_string()])
+            .inc();
+    }
+
+    pub fn record_session_expired(&self, account_id: u64) {
+        self.session_expired
+            .with_label_values(&[&account_id.to_string()])
+            .inc();
+    }
+
+    pub fn record_skill_metrics(
+        &self,
+        character_name: &str,
+        skill_type: &SkillType,
+        skill_metrics: &Metrics,
+    ) {
+        let labels = [&account_label(), character_name, &skill_type.to_string()];
+        self.skill_experience
+            .with_label_values(&labels)
+            .set(skill_metrics.total_experience as i64);
+        self.skill_items_gathered
+            .with_label_values(&labels)
+            .set(skill_metrics.items_gathered as i64);
+    }
+
+    pub fn record_character(&self, character_info: &CharacterInfo) {
+        let account = account_label();
+        let labels = [account.as_str(), character_info.name.as_str()];
+        self.gold
+            .with_label_values(&labels)
+            .set(character_info.gold as i64);
+        self.tokens
+            .with_label_values(&labels)
+            .set(character_info.tokens as i64);
+        self.shards
+            .with_label_values(&labels)
+            .set(character_info.shards as i64);
+        self.health
+            .with_label_values(&labels)
+            .set(character_info.health as i64);
+        self.max_health
+            .with_label_values(&labels)
+            .set(character_info.max_health as i64);
+
+        self.skill_level
+            .with_label_values(&[account.as_str(), character_info.name.as_str(), "combat"])
+            .set(character_info.combat_level as i64);
+        for (skill_type, level) in &character_info.skill_level {
+            self.skill_level
+                .with_label_values(&[
+                    account.as_str(),
+                    character_info.name.as_str(),
+                    &skill_type.to_string(),
+                ])
+                .set(*level as i64);
+        }
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> Result<String> {
+        let mut encoded_metrics = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut encoded_metrics)
+            .map_err(|e| AppError::Application(e.to_string()))?;
+        String::from_utf8(encoded_metrics).map_err(|e| AppError::Application(e.to_string()))
+    }
+}
+
+async fn metrics_handler() -> impl IntoResponse {
+    match METRICS.render() {
+        Ok(rendered_metrics) => (
+            [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+            rendered_metrics,
+        )
+            .into_response(),
+        Err(e) => {
+            warn!(error = %e, "Failed to render metrics.");
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Serves `/metrics` on the given address until the process exits.
+pub async fn serve(listen_addr: SocketAddr) -> Result<()> {
+    let router = Router::new().route("/metrics", get(metrics_handler));
+    let listener = tokio::net::TcpListener::bind(listen_addr).await?;
+    info!(%listen_addr, "Serving Prometheus metrics.");
+    axum::serve(listener, router).await?;
+    Ok(())
+}
@@ -79,7 +79,7 @@ where
     Ok(TimeDelta::milliseconds(milliseconds_value as i64))
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Action {
     #[serde(rename = "type", deserialize_with = "deserialize_capitalize")]
     pub skill_type: SkillType,
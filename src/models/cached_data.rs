@@ -1,10 +1,21 @@
 use std::fmt::Debug;
 
-use super::{character::CharacterInfo, location::Location};
+use super::{
+    character::CharacterInfo,
+    dungeon::Dungeon,
+    location::{LocationFailure, TravelOrigin, WorldLocation},
+};
 
 #[derive(Default)]
 pub struct CachedData {
-    pub locations: Vec<Location>,
+    /// Every location as the game returns it, before per-character filtering.
+    pub locations: Vec<WorldLocation>,
+    /// Locations missing from `locations` because their details failed.
+    pub location_failures: Vec<LocationFailure>,
+    /// Where the character stood when `locations` were fetched.
+    pub locations_origin: Option<TravelOrigin>,
+    /// Every dungeon without the character's cooldown, once fetched.
+    pub dungeons: Option<Vec<Dungeon>>,
     pub character_info: CharacterInfo,
     pub csrf_token: String,
     pub html: String,
@@ -14,6 +25,9 @@ impl Debug for CachedData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("CachedData")
             .field("locations", &self.locations)
+            .field("location_failures", &self.location_failures)
+            .field("locations_origin", &self.locations_origin)
+            .field("dungeons", &self.dungeons)
             .field("character_info", &self.character_info)
             .field("csrf_token", &self.csrf_token)
             .finish()
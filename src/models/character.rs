@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 
 use super::skill::SkillType;
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct CharacterInfo {
     pub id: u64,
     pub name: String,
@@ -16,6 +16,8 @@ pub struct CharacterInfo {
     pub gold: u64,
     pub tokens: u64,
     pub shards: u64,
+    #[serde(default)]
+    pub essence_crystals: Option<u64>,
     pub health: u64,
     pub max_health: u64,
     pub location_id: u64,
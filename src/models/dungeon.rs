@@ -0,0 +1,71 @@
+use chrono::TimeDelta;
+use serde::{Deserialize, Serialize};
+
+use super::item::Item;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Dungeon {
+    pub id: u64,
+    pub name: String,
+    #[serde(alias = "level")]
+    pub level_required: u64,
+    #[serde(alias = "cost", default)]
+    pub gold_cost: u64,
+    #[serde(default)]
+    pub wait_length_ms: Option<u64>,
+    #[serde(default)]
+    pub cooldown_remaining_ms: Option<u64>,
+    #[serde(default)]
+    pub location_id: u64,
+}
+
+impl Dungeon {
+    pub fn is_on_cooldown(&self) -> bool {
+        self.cooldown_remaining_ms
+            .is_some_and(|remaining| remaining > 0)
+    }
+
+    pub fn cooldown_remaining(&self) -> TimeDelta {
+        TimeDelta::milliseconds(self.cooldown_remaining_ms.unwrap_or_default() as i64)
+    }
+
+    /// The details shared by every character.
+    pub fn without_cooldown(self) -> Self {
+        Self {
+            cooldown_remaining_ms: None,
+            ..self
+        }
+    }
+}
+
+/// A dungeon whose details could not be fetched.
+#[derive(Debug, Clone)]
+pub struct DungeonFailure {
+    pub dungeon_id: u64,
+    pub error: String,
+}
+
+/// Dungeons whose details were fetched, by level required, and those that failed.
+#[derive(Debug, Clone, Default)]
+pub struct DungeonFetch {
+    pub dungeons: Vec<Dungeon>,
+    pub failures: Vec<DungeonFailure>,
+}
+
+impl DungeonFetch {
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct DungeonRewards {
+    #[serde(default)]
+    pub gold: u64,
+    #[serde(default)]
+    pub experience: u64,
+    #[serde(default)]
+    pub items: Vec<Item>,
+    #[serde(default)]
+    pub message: String,
+}
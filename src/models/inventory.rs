@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct InventoryItem {
+    #[serde(alias = "inventory_id")]
+    pub id: u64,
+    #[serde(default)]
+    pub item_id: u64,
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub item_type: String,
+    #[serde(default)]
+    pub quantity: u64,
+    #[serde(default)]
+    pub heal_amount: Option<u64>,
+}
+
+impl InventoryItem {
+    pub fn is_healing(&self) -> bool {
+        self.heal_amount.is_some_and(|heal_amount| heal_amount > 0) && self.quantity > 0
+    }
+}
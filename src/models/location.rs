@@ -1,9 +1,17 @@
+use std::{cmp::Reverse, collections::BTreeMap, ops::RangeInclusive};
+
 use serde::{Deserialize, Serialize};
 
-use super::{item::Item, skill::SkillItem};
+use super::{
+    character::CharacterInfo,
+    item::Item,
+    skill::{SkillItem, SkillType},
+};
 
+/// A location with everything in it. `distance` and `teleport_cost` are measured from
+/// where the character that fetched it stood.
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, PartialOrd, Ord, Eq)]
-pub struct Location {
+pub struct WorldLocation {
     pub id: u64,
     pub key: String,
     pub name: String,
@@ -15,9 +23,250 @@ pub struct Location {
     pub skill_items: Vec<SkillItem>,
 }
 
+/// Narrows world locations down to the enemies and skill items that fit some levels.
+/// Filters compose, e.g. `LocationFilter::unlocked_at(40).with_enemy_levels(30..=40)`.
+/// The default filter keeps everything.
+#[derive(Debug, Clone)]
+pub struct LocationFilter {
+    /// Level per skill, overriding `default_skill_level`.
+    skill_levels: BTreeMap<SkillType, u64>,
+    /// Level of skills not in `skill_levels`; `None` keeps every skill item.
+    default_skill_level: Option<u64>,
+    enemy_levels: RangeInclusive<u64>,
+}
+
+impl Default for LocationFilter {
+    fn default() -> Self {
+        Self {
+            skill_levels: BTreeMap::new(),
+            default_skill_level: None,
+            enemy_levels: 0..=u64::MAX,
+        }
+    }
+}
+
+impl LocationFilter {
+    /// Skill items the character has the level for and enemies up to its combat level.
+    pub fn unlocked_now(character_info: &CharacterInfo) -> Self {
+        Self {
+            skill_levels: character_info.skill_level.clone(),
+            default_skill_level: Some(0),
+            enemy_levels: 0..=character_info.combat_level,
+        }
+    }
+
+    /// Skill items and enemies available with every skill and combat at `level`.
+    pub fn unlocked_at(level: u64) -> Self {
+        Self {
+            skill_levels: BTreeMap::new(),
+            default_skill_level: Some(level),
+            enemy_levels: 0..=level,
+        }
+    }
+
+    /// Only keeps enemies within `enemy_levels` as well.
+    pub fn with_enemy_levels(mut self, enemy_levels: RangeInclusive<u64>) -> Self {
+        let lowest_level = (*self.enemy_levels.start()).max(*enemy_levels.start());
+        let highest_level = (*self.enemy_levels.end()).min(*enemy_levels.end());
+        self.enemy_levels = lowest_level..=highest_level;
+        self
+    }
+
+    fn allows_skill_item(&self, skill_item: &SkillItem) -> bool {
+        self.skill_levels
+            .get(&skill_item.skill_type)
+            .copied()
+            .or(self.default_skill_level)
+            .is_none_or(|skill_level| skill_level >= skill_item.level_required)
+    }
+
+    /// Locations with the enemies and skill items that pass, farthest first. Locations
+    /// left with neither are dropped.
+    pub fn apply(&self, world_locations: &[WorldLocation]) -> Vec<WorldLocation> {
+        let mut filtered_locations: Vec<WorldLocation> = world_locations
+            .iter()
+            .filter_map(|world_location| {
+                let mut filtered_location = world_location.clone();
+                filtered_location
+                    .enemies
+                    .retain(|enemy| self.enemy_levels.contains(&enemy.level));
+                filtered_location
+                    .skill_items
+                    .retain(|skill_item| self.allows_skill_item(skill_item));
+                let enemies_empty = filtered_location.enemies.is_empty();
+                let skill_items_empty = filtered_location.skill_items.is_empty();
+                (!enemies_empty || !skill_items_empty).then_some(filtered_location)
+            })
+            .collect();
+        filtered_locations.sort_by_key(|filtered_location| Reverse(filtered_location.distance));
+        filtered_locations
+    }
+}
+
+/// Where a character stood when fetching locations, which their travel costs depend on.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TravelOrigin {
+    pub character_id: u64,
+    pub location_id: u64,
+}
+
+impl TravelOrigin {
+    pub fn of(character_info: &CharacterInfo) -> Self {
+        Self {
+            character_id: character_info.id,
+            location_id: character_info.location_id,
+        }
+    }
+}
+
+/// A location whose details could not be fetched.
+#[derive(Debug, Clone)]
+pub struct LocationFailure {
+    pub location_id: u64,
+    pub error: String,
+}
+
+/// Locations whose details were fetched, in the order the game lists them, and those
+/// that failed.
+#[derive(Debug, Clone, Default)]
+pub struct LocationFetch {
+    pub locations: Vec<WorldLocation>,
+    pub failures: Vec<LocationFailure>,
+}
+
+impl LocationFetch {
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub enum TravelMode {
     Walk,
     Teleport,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enemy(level: u64) -> Item {
+        Item {
+            id: level,
+            name: format!("Enemy {level}"),
+            level,
+        }
+    }
+
+    fn skill_item(skill_type: SkillType, level_required: u64) -> SkillItem {
+        SkillItem {
+            id: level_required,
+            skill_type,
+            level_required,
+            ..Default::default()
+        }
+    }
+
+    fn location(id: u64, distance: u64) -> WorldLocation {
+        WorldLocation {
+            id,
+            distance,
+            enemies: vec![enemy(5), enemy(20), enemy(40)],
+            skill_items: vec![
+                skill_item(SkillType::Mining, 1),
+                skill_item(SkillType::Mining, 30),
+                skill_item(SkillType::Fishing, 10),
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn enemy_levels(location: &WorldLocation) -> Vec<u64> {
+        location.enemies.iter().map(|enemy| enemy.level).collect()
+    }
+
+    fn skill_items(location: &WorldLocation) -> Vec<(SkillType, u64)> {
+        location
+            .skill_items
+            .iter()
+            .map(|skill_item| (skill_item.skill_type.clone(), skill_item.level_required))
+            .collect()
+    }
+
+    #[test]
+    fn default_filter_keeps_everything() {
+        let filtered_locations = LocationFilter::default().apply(&[location(1, 0)]);
+        assert_eq!(filtered_locations, [location(1, 0)]);
+    }
+
+    #[test]
+    fn unlocked_now_keeps_enemies_up_to_the_combat_level() {
+        let character_info = CharacterInfo {
+            combat_level: 20,
+            ..Default::default()
+        };
+        let filtered_locations =
+            LocationFilter::unlocked_now(&character_info).apply(&[location(1, 0)]);
+        assert_eq!(enemy_levels(&filtered_locations[0]), [5, 20]);
+    }
+
+    #[test]
+    fn unlocked_now_keeps_skill_items_per_skill_level() {
+        let character_info = CharacterInfo {
+            skill_level: BTreeMap::from([(SkillType::Mining, 30)]),
+            ..Default::default()
+        };
+        let filtered_locations =
+            LocationFilter::unlocked_now(&character_info).apply(&[location(1, 0)]);
+        // Fishing has no level yet, so nothing above level 0 is unlocked for it.
+        assert_eq!(
+            skill_items(&filtered_locations[0]),
+            [(SkillType::Mining, 1), (SkillType::Mining, 30)]
+        );
+    }
+
+    #[test]
+    fn unlocked_at_applies_one_level_to_every_skill_and_combat() {
+        let filtered_locations = LocationFilter::unlocked_at(10).apply(&[location(1, 0)]);
+        assert_eq!(enemy_levels(&filtered_locations[0]), [5]);
+        assert_eq!(
+            skill_items(&filtered_locations[0]),
+            [(SkillType::Mining, 1), (SkillType::Fishing, 10)]
+        );
+    }
+
+    #[test]
+    fn with_enemy_levels_narrows_the_enemy_band() {
+        let filtered_locations = LocationFilter::unlocked_at(40)
+            .with_enemy_levels(10..=50)
+            .apply(&[location(1, 0)]);
+        assert_eq!(enemy_levels(&filtered_locations[0]), [20, 40]);
+
+        let filtered_locations = LocationFilter::default()
+            .with_enemy_levels(10..=30)
+            .with_enemy_levels(0..=20)
+            .apply(&[location(1, 0)]);
+        assert_eq!(enemy_levels(&filtered_locations[0]), [20]);
+    }
+
+    #[test]
+    fn drops_empty_locations_and_sorts_farthest_first() {
+        let empty_location = WorldLocation {
+            id: 3,
+            distance: 50,
+            enemies: vec![enemy(40)],
+            ..Default::default()
+        };
+        let filtered_locations = LocationFilter::unlocked_at(10).apply(&[
+            location(1, 5),
+            empty_location,
+            location(2, 9),
+        ]);
+        let location_ids: Vec<u64> = filtered_locations
+            .iter()
+            .map(|location| location.id)
+            .collect();
+        assert_eq!(location_ids, [2, 1]);
+    }
+}
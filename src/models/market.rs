@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct MarketListing {
+    #[serde(alias = "listing_id")]
+    pub id: u64,
+    pub item_id: u64,
+    #[serde(alias = "name")]
+    pub item_name: String,
+    #[serde(alias = "price")]
+    pub unit_price: u64,
+    pub quantity: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeSide {
+    Buy,
+    List,
+    VendorSell,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Trade {
+    pub at: DateTime<Utc>,
+    pub character_id: u64,
+    pub item_id: u64,
+    pub item_name: String,
+    pub quantity: u64,
+    pub unit_price: u64,
+    pub side: TradeSide,
+}
+
+impl Trade {
+    /// Gold gained (positive) or spent (negative) by this trade. Creating a listing
+    /// realizes nothing until it is filled.
+    pub fn gold_delta(&self) -> i64 {
+        let total_gold =
+            i64::try_from(self.quantity.saturating_mul(self.unit_price)).unwrap_or(i64::MAX);
+        match self.side {
+            TradeSide::Buy => -total_gold,
+            TradeSide::VendorSell => total_gold,
+            TradeSide::List => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SellMode {
+    #[default]
+    Market,
+    Vendor,
+}
+
+#[derive(Debug, Clone)]
+pub struct MarketPolicy {
+    /// Highest unit price paid for a recipe input, keyed by item name.
+    pub max_unit_price: BTreeMap<String, u64>,
+    pub default_max_unit_price: u64,
+    /// Quantity of an item kept in the inventory before the surplus is sold.
+    pub keep_thresholds: BTreeMap<String, u64>,
+    pub default_keep_threshold: u64,
+    pub sell_mode: SellMode,
+    /// Gold subtracted from the cheapest listing when listing surplus.
+    pub undercut: u64,
+}
+
+impl Default for MarketPolicy {
+    fn default() -> Self {
+        Self {
+            max_unit_price: BTreeMap::new(),
+            default_max_unit_price: 0,
+            keep_thresholds: BTreeMap::new(),
+            default_keep_threshold: u64::MAX,
+            sell_mode: SellMode::default(),
+            undercut: 1,
+        }
+    }
+}
+
+impl MarketPolicy {
+    pub fn price_cap(&self, item_name: &str) -> u64 {
+        self.max_unit_price
+            .get(item_name)
+            .copied()
+            .unwrap_or(self.default_max_unit_price)
+    }
+
+    pub fn keep_threshold(&self, item_name: &str) -> u64 {
+        self.keep_thresholds
+            .get(item_name)
+            .copied()
+            .unwrap_or(self.default_keep_threshold)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Ledger {
+    pub trades: Vec<Trade>,
+}
+
+impl Ledger {
+    pub fn new(trades: Vec<Trade>) -> Self {
+        Self { trades }
+    }
+
+    pub fn profit_by_character(&self) -> BTreeMap<u64, i64> {
+        let mut profit_by_character = BTreeMap::new();
+        for trade in &self.trades {
+            *profit_by_character.entry(trade.character_id).or_default() += trade.gold_delta();
+        }
+        profit_by_character
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(character_id: u64, quantity: u64, unit_price: u64, side: TradeSide) -> Trade {
+        Trade {
+            at: Utc::now(),
+            character_id,
+            item_id: 1,
+            item_name: "Copper Ore".to_string(),
+            quantity,
+            unit_price,
+            side,
+        }
+    }
+
+    #[test]
+    fn gold_delta_is_negative_for_buys_and_positive_for_vendor_sales() {
+        assert_eq!(trade(1, 3, 10, TradeSide::Buy).gold_delta(), -30);
+        assert_eq!(trade(1, 3, 10, TradeSide::VendorSell).gold_delta(), 30);
+    }
+
+    #[test]
+    fn gold_delta_ignores_listings() {
+        assert_eq!(trade(1, 3, 10, TradeSide::List).gold_delta(), 0);
+    }
+
+    #[test]
+    fn gold_delta_saturates_instead_of_overflowing() {
+        assert_eq!(
+            trade(1, u64::MAX, 2, TradeSide::Buy).gold_delta(),
+            -i64::MAX
+        );
+        assert_eq!(
+            trade(1, u64::MAX, 2, TradeSide::VendorSell).gold_delta(),
+            i64::MAX
+        );
+    }
+
+    #[test]
+    fn profit_by_character_sums_each_character_separately() {
+        let ledger = Ledger::new(vec![
+            trade(1, 2, 50, TradeSide::Buy),
+            trade(1, 4, 40, TradeSide::VendorSell),
+            trade(1, 9, 99, TradeSide::List),
+            trade(2, 1, 25, TradeSide::Buy),
+        ]);
+
+        assert_eq!(
+            ledger.profit_by_character(),
+            BTreeMap::from([(1, 60), (2, -25)])
+        );
+    }
+
+    #[test]
+    fn profit_by_character_is_empty_without_trades() {
+        assert!(Ledger::default().profit_by_character().is_empty());
+    }
+}
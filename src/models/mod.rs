@@ -1,14 +1,20 @@
 pub mod action;
 pub mod cached_data;
 pub mod character;
+pub mod dungeon;
+pub mod inventory;
 pub mod item;
 pub mod location;
+pub mod market;
 pub mod skill;
 pub mod user;
 
 pub use action::*;
 pub use cached_data::*;
 pub use character::*;
+pub use dungeon::*;
+pub use inventory::*;
+pub use market::*;
 use serde::{Deserialize, Serialize};
 pub use skill::*;
 pub use user::*;
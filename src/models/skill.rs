@@ -23,6 +23,7 @@ pub enum SkillType {
     Forge,
     Meditation,
     Travelling,
+    Dungeon,
 }
 
 impl SkillType {
@@ -34,6 +35,10 @@ impl SkillType {
         }
         Err(AppError::Parse("Failed to parse skill type.".into()))
     }
+    /// Whether `start_skill` can start it, unlike travelling and dungeons.
+    pub fn is_startable(&self) -> bool {
+        !matches!(self, Self::None | Self::Travelling | Self::Dungeon)
+    }
 }
 
 impl std::fmt::Display for SkillType {
@@ -85,11 +90,23 @@ pub struct SkillItem {
     pub skill_type: SkillType,
     pub level_required: u64,
     pub wait_length_ms: Option<u64>,
+    #[serde(default)]
+    pub experience: Option<u64>,
     #[serde(default, deserialize_with = "extract_requirements_item")]
     pub requirements: Vec<SkillItem>,
     pub quantity_requirement: Option<u64>,
 }
 
+impl SkillItem {
+    pub fn experience_per_hour(&self) -> Option<f64> {
+        let experience = self.experience? as f64;
+        let wait_length_ms = self
+            .wait_length_ms
+            .filter(|wait_length_ms| *wait_length_ms > 0)?;
+        Some(experience * 3_600_000.0 / wait_length_ms as f64)
+    }
+}
+
 fn duration_from_str<'de, D, E>(deserializer: D) -> std::result::Result<Duration, E>
 where
     D: Deserializer<'de>,
@@ -140,7 +157,7 @@ where
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct Metrics {
     #[serde(deserialize_with = "number_from_string", default)]
     pub items_gathered: u64,
@@ -157,7 +174,7 @@ pub struct SkillData {
     pub metrics: Metrics,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct SkillRequestData {
     pub skill_item_id: u64,
     pub quantity: u64,
@@ -167,7 +184,8 @@ pub struct SkillRequestData {
     pub auto_purchase: bool,
 }
 
-#[derive(Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum FilterBy {
     #[default]
     HighestLevelRequired,
@@ -179,7 +197,8 @@ pub enum FilterBy {
     ItemName(String),
 }
 
-#[derive(Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
 pub struct SkillConfig {
     pub skill_type: SkillType,
     pub essence_crystal: u64,
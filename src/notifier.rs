@@ -0,0 +1,403 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::{AppError, Result},
+    events::{BotEvent, EventEnvelope},
+};
+
+/// Longest wait for a webhook, so a hanging one does not hold up later events.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Events sent when a webhook does not list any.
+const DEFAULT_EVENTS: [&str; 5] = [
+    "session_expired",
+    "two_factor_required",
+    "level_up",
+    "action_failed",
+    "gold_changed",
+];
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// `{"message": ..., "event": {...}}`
+    #[default]
+    Json,
+    Discord,
+    Slack,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub url: String,
+    pub format: WebhookFormat,
+    /// Event types to send, e.g. `level_up`. Empty means [`DEFAULT_EVENTS`].
+    pub events: Vec<String>,
+    /// Message templates by event type. `{field}` is replaced with the event field,
+    /// `{message}` with the default message.
+    pub templates: HashMap<String, String>,
+    /// Minimum time between two messages of the same event type and account.
+    pub min_interval_secs: u64,
+    /// Only send `gold_changed` when gold falls below this amount.
+    pub gold_below: Option<u64>,
+    /// Only send `action_failed` once a failure streak reaches this length.
+    pub min_consecutive_failures: u32,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            format: WebhookFormat::default(),
+            events: vec![],
+            templates: HashMap::new(),
+            min_interval_secs: 300,
+            gold_below: None,
+            min_consecutive_failures: 3,
+        }
+    }
+}
+
+impl WebhookConfig {
+    fn accepts(&self, event: &BotEvent) -> bool {
+        let event_kind = event.kind();
+        let is_listed = if self.events.is_empty() {
+            DEFAULT_EVENTS.contains(&event_kind)
+        } else {
+            self.events
+                .iter()
+                .any(|listed_kind| listed_kind == event_kind)
+        };
+        if !is_listed {
+            return false;
+        }
+
+        match event {
+            // Only the drop across the threshold, not every change below it.
+            BotEvent::GoldChanged {
+                previous, current, ..
+            } => self
+                .gold_below
+                .is_some_and(|gold_below| *current < gold_below && *previous >= gold_below),
+            BotEvent::ActionFailed {
+                consecutive_failures,
+                ..
+            } => *consecutive_failures == self.min_consecutive_failures.max(1),
+            _ => true,
+        }
+    }
+
+    fn render(&self, event_envelope: &EventEnvelope) -> String {
+        let message = default_message(event_envelope);
+        let Some(template) = self.templates.get(event_envelope.event.kind()) else {
+            return message;
+        };
+
+        let mut rendered_message = template.replace("{message}", &message);
+        if let Ok(Value::Object(event_fields)) = serde_json::to_value(event_envelope) {
+            for (field_name, field_value) in event_fields {
+                let field_text = match field_value {
+                    Value::String(text) => text,
+                    Value::Null => String::new(),
+                    other => other.to_string(),
+                };
+                rendered_message =
+                    rendered_message.replace(&format!("{{{field_name}}}"), &field_text);
+            }
+        }
+        rendered_message
+    }
+
+    fn payload(&self, event_envelope: &EventEnvelope, message: &str) -> Value {
+        match self.format {
+            WebhookFormat::Json => json!({ "message": message, "event": event_envelope }),
+            WebhookFormat::Discord => json!({ "content": message }),
+            WebhookFormat::Slack => json!({ "text": message }),
+        }
+    }
+}
+
+fn default_message(event_envelope: &EventEnvelope) -> String {
+    let account = event_envelope
+        .account_id
+        .map(|account_id| format!("[account {account_id}] "))
+        .unwrap_or_default();
+    let message = match &event_envelope.event {
+        BotEvent::SessionExpired { account_id } => {
+            format!("Session of account {account_id} expired, log in again.")
+        }
+        BotEvent::TwoFactorRequired { email, attempt } => {
+            format!("{email} is waiting for a 2FA code (attempt {attempt}).")
+        }
+        BotEvent::LevelUp {
+            character,
+            skill,
+            level,
+        } => match skill {
+            Some(skill_type) => format!("{character} reached {skill_type} level {level}."),
+            None => format!("{character} reached combat level {level}."),
+        },
+        BotEvent::ActionFailed {
+            error,
+            consecutive_failures,
+        } => format!("Action failed {consecutive_failures} times in a row: {error}"),
+        BotEvent::GoldChanged {
+            character,
+            previous,
+            current,
+        } => format!("{character}'s gold dropped from {previous} to {current}."),
+        other_event => format!("{other_event:?}"),
+    };
+    format!("{account}{message}")
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct NotifierConfig {
+    pub webhooks: Vec<WebhookConfig>,
+}
+
+impl NotifierConfig {
+    pub async fn load(path: &Path) -> Result<Self> {
+        let raw_config = tokio::fs::read_to_string(path).await?;
+        let notifier_config: Self = serde_json::from_str(&raw_config)?;
+        if let Some(webhook) = notifier_config
+            .webhooks
+            .iter()
+            .find(|webhook| reqwest::Url::parse(&webhook.url).is_err())
+        {
+            return Err(AppError::Config(format!(
+                "Invalid webhook URL: {:?}",
+                webhook.url
+            )));
+        }
+        Ok(notifier_config)
+    }
+}
+
+#[derive(Debug)]
+pub struct Notifier {
+    config: NotifierConfig,
+    client: Client,
+    /// Last send per webhook index, event type and account.
+    last_sent: HashMap<(usize, &'static str, Option<u64>), Instant>,
+}
+
+impl Notifier {
+    pub fn new(config: NotifierConfig) -> Result<Self> {
+        Ok(Self {
+            config,
+            client: Client::builder().timeout(WEBHOOK_TIMEOUT).build()?,
+            last_sent: HashMap::new(),
+        })
+    }
+
+    fn is_rate_limited(&mut self, webhook_index: usize, event_envelope: &EventEnvelope) -> bool {
+        let min_interval =
+            Duration::from_secs(self.config.webhooks[webhook_index].min_interval_secs);
+        let rate_limit_key = (
+            webhook_index,
+            event_envelope.event.kind(),
+            event_envelope.account_id,
+        );
+        let now = Instant::now();
+        if let Some(last_sent_at) = self.last_sent.get(&rate_limit_key)
+            && now.duration_since(*last_sent_at) < min_interval
+        {
+            return true;
+        }
+        self.last_sent.insert(rate_limit_key, now);
+        false
+    }
+
+    /// Sends the event to every webhook whose filters and rate limit allow it.
+    pub async fn notify(&mut self, event_envelope: &EventEnvelope) {
+        for webhook_index in 0..self.config.webhooks.len() {
+            if !self.config.webhooks[webhook_index].accepts(&event_envelope.event) {
+                continue;
+            }
+            if self.is_rate_limited(webhook_index, event_envelope) {
+                debug!(
+                    event = event_envelope.event.kind(),
+                    "Notification rate limited."
+                );
+                continue;
+            }
+
+            let webhook = &self.config.webhooks[webhook_index];
+            let message = webhook.render(event_envelope);
+            if let Err(e) = self.send(webhook, event_envelope, &message).await {
+                warn!(url = %webhook.url, error = %e, "Failed to send notification.");
+            }
+        }
+    }
+
+    async fn send(
+        &self,
+        webhook: &WebhookConfig,
+        event_envelope: &EventEnvelope,
+        message: &str,
+    ) -> Result<()> {
+        let http_response = self
+            .client
+            .post(&webhook.url)
+            .json(&webhook.payload(event_envelope, message))
+            .send()
+            .await?
+            .error_for_status()?;
+        debug!(status = %http_response.status(), event = event_envelope.event.kind(), "Notification sent.");
+        Ok(())
+    }
+
+    /// Sends a sample event to every webhook, ignoring filters and rate limits.
+    pub async fn send_test(&self) -> Result<()> {
+        let event_envelope = EventEnvelope {
+            at: Utc::now(),
+            account_id: None,
+            event: BotEvent::ActionFailed {
+                error: "Test notification".to_string(),
+                consecutive_failures: 1,
+            },
+        };
+        for webhook in &self.config.webhooks {
+            let message = webhook.render(&event_envelope);
+            self.send(webhook, &event_envelope, &message).await?;
+            info!(url = %webhook.url, "Test notification sent.");
+        }
+        Ok(())
+    }
+
+    /// Forwards events from the bus until it is closed.
+    pub async fn run(mut self, mut events: broadcast::Receiver<EventEnvelope>) {
+        info!(webhooks = self.config.webhooks.len(), "Notifier started.");
+        loop {
+            match events.recv().await {
+                Ok(event_envelope) => self.notify(&event_envelope).await,
+                Err(RecvError::Lagged(skipped_events)) => {
+                    warn!(skipped_events, "Notifier fell behind, events skipped.");
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{Json, Router, extract::State, routing::post};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    type ReceivedPayloads = Arc<Mutex<Vec<Value>>>;
+
+    /// Serves a webhook sink on a free local port and returns its URL.
+    async fn serve_sink(received_payloads: ReceivedPayloads) -> String {
+        async fn receive(
+            State(received_payloads): State<ReceivedPayloads>,
+            Json(payload): Json<Value>,
+        ) {
+            received_payloads.lock().unwrap().push(payload);
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let sink_url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let router = Router::new()
+            .route("/hook", post(receive))
+            .with_state(received_payloads);
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        sink_url
+    }
+
+    fn level_up(account_id: u64) -> EventEnvelope {
+        EventEnvelope {
+            at: Utc::now(),
+            account_id: Some(account_id),
+            event: BotEvent::LevelUp {
+                character: "Tester".to_string(),
+                skill: None,
+                level: 12,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn delivers_rendered_templates_and_rate_limits_per_account() {
+        let received_payloads = ReceivedPayloads::default();
+        let sink_url = serve_sink(received_payloads.clone()).await;
+        let webhook = WebhookConfig {
+            url: sink_url,
+            format: WebhookFormat::Discord,
+            templates: HashMap::from([(
+                "level_up".to_string(),
+                "{character} is now level {level} ({message})".to_string(),
+            )]),
+            ..Default::default()
+        };
+        let mut notifier = Notifier::new(NotifierConfig {
+            webhooks: vec![webhook],
+        })
+        .unwrap();
+
+        notifier.notify(&level_up(1)).await;
+        notifier.notify(&level_up(1)).await;
+        notifier.notify(&level_up(2)).await;
+
+        let received_payloads = received_payloads.lock().unwrap();
+        assert_eq!(received_payloads.len(), 2);
+        assert_eq!(
+            received_payloads[0],
+            json!({
+                "content": "Tester is now level 12 ([account 1] Tester reached combat level 12.)"
+            })
+        );
+        assert_eq!(
+            received_payloads[1]["content"],
+            "Tester is now level 12 ([account 2] Tester reached combat level 12.)"
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_unlisted_events() {
+        let received_payloads = ReceivedPayloads::default();
+        let sink_url = serve_sink(received_payloads.clone()).await;
+        let mut notifier = Notifier::new(NotifierConfig {
+            webhooks: vec![WebhookConfig {
+                url: sink_url,
+                events: vec!["session_expired".to_string()],
+                ..Default::default()
+            }],
+        })
+        .unwrap();
+
+        notifier.notify(&level_up(1)).await;
+        notifier
+            .notify(&EventEnvelope {
+                at: Utc::now(),
+                account_id: Some(1),
+                event: BotEvent::SessionExpired { account_id: 1 },
+            })
+            .await;
+
+        let received_payloads = received_payloads.lock().unwrap();
+        assert_eq!(received_payloads.len(), 1);
+        assert_eq!(
+            received_payloads[0]["message"],
+            "[account 1] Session of account 1 expired, log in again."
+        );
+        assert_eq!(received_payloads[0]["event"]["type"], "session_expired");
+    }
+}
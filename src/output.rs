@@ -0,0 +1,405 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Result,
+    models::{
+        Account, Action, Character, CharacterInfo, FilterBy, SkillData, SkillType,
+        location::WorldLocation,
+    },
+    supervisor::{AccountState, SupervisorStatus},
+    utils::obfuscate_email,
+};
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+/// A record that can be flattened into one or more table or CSV rows.
+pub trait Tabular {
+    fn headers() -> Vec<&'static str>;
+    fn rows(&self) -> Vec<Vec<String>>;
+}
+
+/// Accounts without their API token and cookie, safe to print.
+#[derive(Serialize, Debug)]
+pub struct AccountSummary {
+    pub id: u64,
+    pub email: String,
+}
+
+impl From<&Account> for AccountSummary {
+    fn from(account: &Account) -> Self {
+        Self {
+            id: account.id,
+            email: obfuscate_email(&account.email),
+        }
+    }
+}
+
+impl Tabular for AccountSummary {
+    fn headers() -> Vec<&'static str> {
+        vec!["id", "email"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![self.id.to_string(), self.email.clone()]]
+    }
+}
+
+/// Gold gained (positive) or spent (negative) through trades by one character.
+#[derive(Serialize, Debug)]
+pub struct CharacterProfit {
+    pub character_id: u64,
+    pub gold_delta: i64,
+}
+
+impl Tabular for CharacterProfit {
+    fn headers() -> Vec<&'static str> {
+        vec!["character_id", "gold_delta"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.character_id.to_string(),
+            self.gold_delta.to_string(),
+        ]]
+    }
+}
+
+/// The part of a supervisor's state that control clients print.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SupervisorSummary {
+    pub account_id: u64,
+    pub email: String,
+    pub status: SupervisorStatus,
+    pub character: String,
+    pub location: String,
+    pub skill: SkillType,
+    pub filter_by: FilterBy,
+    pub action: Option<String>,
+    pub remaining_secs: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+impl From<&AccountState> for SupervisorSummary {
+    fn from(account_state: &AccountState) -> Self {
+        Self {
+            account_id: account_state.account_id,
+            email: obfuscate_email(&account_state.email),
+            status: account_state.status,
+            character: account_state.character_info.name.clone(),
+            location: account_state.location_name.clone(),
+            skill: account_state.skill.clone(),
+            filter_by: account_state.filter_by.clone(),
+            action: account_state
+                .active_action
+                .as_ref()
+                .map(|active_action| active_action.item_name.clone()),
+            remaining_secs: account_state
+                .remaining_time()
+                .map(|remaining_time| remaining_time.num_seconds()),
+            last_error: account_state.last_error.clone(),
+        }
+    }
+}
+
+impl Tabular for SupervisorSummary {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "account_id",
+            "email",
+            "status",
+            "character",
+            "location",
+            "skill",
+            "filter_by",
+            "action",
+            "remaining_secs",
+            "last_error",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.account_id.to_string(),
+            self.email.clone(),
+            format!("{:?}", self.status),
+            self.character.clone(),
+            self.location.clone(),
+            self.skill.to_string(),
+            format!("{:?}", self.filter_by),
+            self.action.clone().unwrap_or_default(),
+            self.remaining_secs
+                .map(|remaining_secs| remaining_secs.to_string())
+                .unwrap_or_default(),
+            self.last_error.clone().unwrap_or_default(),
+        ]]
+    }
+}
+
+impl Tabular for Character {
+    fn headers() -> Vec<&'static str> {
+        vec!["id", "name", "class", "level", "current"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.id.to_string(),
+            self.name.clone(),
+            self.class_name.clone(),
+            self.level.to_string(),
+            self.is_current.to_string(),
+        ]]
+    }
+}
+
+impl Tabular for CharacterInfo {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "id",
+            "name",
+            "combat_level",
+            "total_level",
+            "gold",
+            "tokens",
+            "shards",
+            "health",
+            "max_health",
+            "location_id",
+            "skill_levels",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let skill_levels = self
+            .skill_level
+            .iter()
+            .map(|(skill_type, level)| format!("{skill_type}={level}"))
+            .collect::<Vec<_>>()
+            .join(";");
+        vec![vec![
+            self.id.to_string(),
+            self.name.clone(),
+            self.combat_level.to_string(),
+            self.total_level
+                .map(|level| level.to_string())
+                .unwrap_or_default(),
+            self.gold.to_string(),
+            self.tokens.to_string(),
+            self.shards.to_string(),
+            self.health.to_string(),
+            self.max_health.to_string(),
+            self.location_id.to_string(),
+            skill_levels,
+        ]]
+    }
+}
+
+impl Tabular for WorldLocation {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "id",
+            "name",
+            "recommended_level",
+            "distance",
+            "teleport_cost",
+            "skill_items",
+            "enemies",
+            "dungeons",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let skill_items = self
+            .skill_items
+            .iter()
+            .map(|skill_item| {
+                format!(
+                    "{} ({} {})",
+                    skill_item.name.as_deref().unwrap_or("?"),
+                    skill_item.skill_type,
+                    skill_item.level_required
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(";");
+        let enemies = self
+            .enemies
+            .iter()
+            .map(|enemy| format!("{} ({})", enemy.name, enemy.level))
+            .collect::<Vec<_>>()
+            .join(";");
+        let dungeons = self
+            .dungeons
+            .iter()
+            .map(|dungeon| format!("{} ({})", dungeon.name, dungeon.level))
+            .collect::<Vec<_>>()
+            .join(";");
+        vec![vec![
+            self.id.to_string(),
+            self.name.clone(),
+            self.recommended_level.to_string(),
+            self.distance.to_string(),
+            self.teleport_cost.to_string(),
+            skill_items,
+            enemies,
+            dungeons,
+        ]]
+    }
+}
+
+impl Tabular for Action {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "skill",
+            "item",
+            "progress",
+            "expires_in_secs",
+            "quantity",
+            "max_quantity",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.skill_type.to_string(),
+            self.item_name.clone(),
+            format!("{:.1}", self.current_progress),
+            self.expires_in.num_seconds().to_string(),
+            self.quantity.to_string(),
+            self.max_quantity.to_string(),
+        ]]
+    }
+}
+
+impl Tabular for SkillData {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "skill",
+            "item_id",
+            "item",
+            "level_required",
+            "wait_length_ms",
+            "experience",
+            "requirements",
+            "items_gathered",
+            "time_spent_secs",
+            "total_experience",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.items
+            .iter()
+            .map(|skill_item| {
+                let requirements = skill_item
+                    .requirements
+                    .iter()
+                    .map(|required_item| {
+                        format!(
+                            "{}x{}",
+                            required_item.name.as_deref().unwrap_or("?"),
+                            required_item.quantity_requirement.unwrap_or(1)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(";");
+                vec![
+                    self.skill_type.to_string(),
+                    skill_item.id.to_string(),
+                    skill_item.name.clone().unwrap_or_default(),
+                    skill_item.level_required.to_string(),
+                    skill_item
+                        .wait_length_ms
+                        .map(|wait_length_ms| wait_length_ms.to_string())
+                        .unwrap_or_default(),
+                    skill_item
+                        .experience
+                        .map(|experience| experience.to_string())
+                        .unwrap_or_default(),
+                    requirements,
+                    self.metrics.items_gathered.to_string(),
+                    self.metrics.time_spent.num_seconds().to_string(),
+                    self.metrics.total_experience.to_string(),
+                ]
+            })
+            .collect()
+    }
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn render_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut column_widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in rows {
+        for (column_width, field) in column_widths.iter_mut().zip(row) {
+            *column_width = (*column_width).max(field.chars().count());
+        }
+    }
+
+    let render_row = |fields: Vec<&str>| {
+        fields
+            .iter()
+            .zip(&column_widths)
+            .map(|(field, column_width)| format!("{field:<column_width$}"))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut rendered_lines = vec![render_row(headers.to_vec())];
+    rendered_lines.extend(
+        rows.iter()
+            .map(|row| render_row(row.iter().map(String::as_str).collect())),
+    );
+    rendered_lines.join("\n")
+}
+
+fn render_rows<T: Tabular>(format: OutputFormat, records: &[&T]) -> String {
+    let headers = T::headers();
+    let rows: Vec<Vec<String>> = records.iter().flat_map(|record| record.rows()).collect();
+    match format {
+        OutputFormat::Csv => std::iter::once(headers.join(","))
+            .chain(rows.iter().map(|row| {
+                row.iter()
+                    .map(|field| escape_csv(field))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => render_table(&headers, &rows),
+    }
+}
+
+/// Prints a list of records to stdout in the requested format.
+pub fn print_list<T: Serialize + Tabular>(format: OutputFormat, records: &[T]) -> Result<()> {
+    let rendered_output = match format {
+        OutputFormat::Json => serde_json::to_string_pretty(records)?,
+        _ => render_rows(format, &records.iter().collect::<Vec<_>>()),
+    };
+    println!("{rendered_output}");
+    Ok(())
+}
+
+/// Prints a single, possibly missing, record; JSON output uses `null` when absent.
+pub fn print_one<T: Serialize + Tabular>(format: OutputFormat, record: Option<&T>) -> Result<()> {
+    let rendered_output = match format {
+        OutputFormat::Json => serde_json::to_string_pretty(&record)?,
+        _ => render_rows(format, &record.into_iter().collect::<Vec<_>>()),
+    };
+    println!("{rendered_output}");
+    Ok(())
+}
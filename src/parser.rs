@@ -5,6 +5,8 @@ use regex::Regex;
 
 use crate::{
     error::{AppError, Result},
+    events::{BotEvent, EVENT_BUS},
+    metrics::METRICS,
     models::SkillType,
 };
 
@@ -24,6 +26,18 @@ pub enum Parser {
     ActionActiveApiEndpoint,
     SkillsStartApiEndpoint,
     SkillsDataApiEndpoint,
+    QuickViewDungeonApiEndpoint,
+    DungeonsStartApiEndpoint,
+    DungeonsClaimApiEndpoint,
+    InventoryAllApiEndpoint,
+    InventoryUseApiEndpoint,
+    MarketListingsApiEndpoint,
+    MarketBuyApiEndpoint,
+    MarketCreateListingApiEndpoint,
+    ShopSellApiEndpoint,
+    TwoFactorCode,
+    ImapInternalDate,
+    GameVersion,
 }
 
 impl Parser {
@@ -50,6 +64,33 @@ impl Parser {
             Self::ActionActiveApiEndpoint => lazy_regex!(r#"(https?.*?/action\\?/active[^'"]+)""#),
             Self::SkillsStartApiEndpoint => lazy_regex!(r#"(https?.*?/skills\\?/start[^'"]+)""#),
             Self::SkillsDataApiEndpoint => lazy_regex!(r#"(https?.*?/skills\\?/data[^'"]+)""#),
+            Self::QuickViewDungeonApiEndpoint => {
+                lazy_regex!(r#"(https?.*?/quick-view\\?/dungeon[^'"]+)"#)
+            }
+            Self::DungeonsStartApiEndpoint => {
+                lazy_regex!(r#"(https?.*?/dungeons\\?/start[^'"]+)""#)
+            }
+            Self::DungeonsClaimApiEndpoint => {
+                lazy_regex!(r#"(https?.*?/dungeons\\?/claim[^'"]+)""#)
+            }
+            Self::InventoryAllApiEndpoint => {
+                lazy_regex!(r#"(https?.*?/inventory\\?/all[^'"]+)""#)
+            }
+            Self::InventoryUseApiEndpoint => {
+                lazy_regex!(r#"(https?.*?/inventory\\?/use[^'"]+)""#)
+            }
+            Self::MarketListingsApiEndpoint => {
+                lazy_regex!(r#"(https?.*?/market\\?/listings[^'"]+)""#)
+            }
+            Self::MarketBuyApiEndpoint => lazy_regex!(r#"(https?.*?/market\\?/buy[^'"]+)""#),
+            Self::MarketCreateListingApiEndpoint => {
+                lazy_regex!(r#"(https?.*?/market\\?/create[^'"]+)""#)
+            }
+            Self::ShopSellApiEndpoint => lazy_regex!(r#"(https?.*?/shop\\?/sell[^'"]+)""#),
+            Self::TwoFactorCode => lazy_regex!(r"\b(\d{6})\b"),
+            Self::ImapInternalDate => lazy_regex!(r#"INTERNALDATE "([^"]+)""#),
+            // Hash of the front-end build, which changes with every game release.
+            Self::GameVersion => lazy_regex!(r"/build/assets/app-([A-Za-z0-9_-]+)\.(?:js|css)"),
         }
     }
 
@@ -59,7 +100,23 @@ impl Parser {
             .captures(input_text)
             .and_then(|caps| caps.get(1))
             .map(|val| val.as_str())
-            .ok_or_else(|| AppError::Parse(format!("Failed to find value for key: {self:?}")))?;
+            .ok_or_else(|| {
+                // Missing 2FA prompts and codes are expected, not page changes. The game
+                // version is optional.
+                if !matches!(
+                    self,
+                    Self::TwoFactorUrl
+                        | Self::TwoFactorCode
+                        | Self::ImapInternalDate
+                        | Self::GameVersion
+                ) {
+                    METRICS.record_parse_failure(self);
+                    EVENT_BUS.publish(BotEvent::ParseFailure {
+                        parser: format!("{self:?}"),
+                    });
+                }
+                AppError::Parse(format!("Failed to find value for key: {self:?}"))
+            })?;
         let decoded_html = decode_html_entities(captured_value).to_string();
         let unescaped_string = decoded_html.replace('\\', "").replace("u0026", "&");
         Ok(unescaped_string)
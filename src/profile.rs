@@ -0,0 +1,375 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use notify::{RecursiveMode, Watcher};
+use serde::Deserialize;
+use tokio::{
+    sync::mpsc::{self, error::TrySendError},
+    time::MissedTickBehavior,
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    error::{AppError, Result},
+    models::{FilterBy, SkillConfig},
+    scheduler::SchedulerOptions,
+    supervisor::SupervisorCommand,
+};
+
+/// Editors write a file in several steps; wait for them to settle before reading it.
+const SETTLE_DELAY: Duration = Duration::from_millis(500);
+/// How often profile changes postponed by busy supervisors are sent again.
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Skill configuration per account, e.g.
+/// `{"default": {"skill_type": "Mining"}, "accounts": {"12": {"skill_type": "Fishing"}}}`.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profiles {
+    /// For accounts without an entry. The command line skill is used when absent.
+    pub default: Option<SkillConfig>,
+    pub accounts: BTreeMap<u64, SkillConfig>,
+}
+
+impl Profiles {
+    pub async fn load(path: &Path) -> Result<Self> {
+        let raw_profiles = tokio::fs::read_to_string(path).await?;
+        let profiles: Self = serde_json::from_str(&raw_profiles)?;
+        if let Some(default_config) = &profiles.default {
+            validate("default", default_config)?;
+        }
+        for (account_id, skill_config) in &profiles.accounts {
+            validate(&format!("account {account_id}"), skill_config)?;
+        }
+        Ok(profiles)
+    }
+
+    /// The configuration an account runs with, falling back to the default profile
+    /// and then to `fallback`.
+    pub fn skill_config(&self, account_id: u64, fallback: &SkillConfig) -> SkillConfig {
+        self.accounts
+            .get(&account_id)
+            .or(self.default.as_ref())
+            .unwrap_or(fallback)
+            .clone()
+    }
+
+    /// Scheduler options for an account, with its profile in place of the base skill.
+    pub fn scheduler_options(&self, account_id: u64, base: &SchedulerOptions) -> SchedulerOptions {
+        SchedulerOptions {
+            skill_config: self.skill_config(account_id, &base.skill_config),
+            ..base.clone()
+        }
+    }
+
+    /// New configurations of the given accounts whose configuration differs from the one
+    /// they got from `previous`. Logs what changed for each.
+    pub fn changes(
+        &self,
+        previous: &Profiles,
+        account_ids: impl IntoIterator<Item = u64>,
+        fallback: &SkillConfig,
+    ) -> Vec<(u64, SkillConfig)> {
+        account_ids
+            .into_iter()
+            .filter_map(|account_id| {
+                let previous_config = previous.skill_config(account_id, fallback);
+                let current_config = self.skill_config(account_id, fallback);
+                let changed_fields = changed_fields(&previous_config, &current_config);
+                if changed_fields.is_empty() {
+                    return None;
+                }
+                info!(
+                    account_id,
+                    changes = changed_fields.join(", "),
+                    "Profile changed."
+                );
+                Some((account_id, current_config))
+            })
+            .collect()
+    }
+}
+
+/// Profile changes not yet handed to their supervisor, by account id.
+#[derive(Debug, Default)]
+pub struct PendingSkillConfigs(HashMap<u64, SkillConfig>);
+
+impl PendingSkillConfigs {
+    /// A newer change replaces one still waiting for its supervisor.
+    pub fn extend(&mut self, changes: Vec<(u64, SkillConfig)>) {
+        self.0.extend(changes);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Hands pending changes to their supervisors without waiting on busy ones, which
+    /// keep theirs for the next attempt. Changes for unknown accounts are dropped.
+    pub fn send<'a>(
+        &mut self,
+        command_sender: impl Fn(u64) -> Option<&'a mpsc::Sender<SupervisorCommand>>,
+    ) {
+        self.0.retain(|&account_id, skill_config| {
+            let Some(command_sender) = command_sender(account_id) else {
+                return false;
+            };
+            match command_sender.try_send(SupervisorCommand::SetSkillConfig(skill_config.clone())) {
+                Ok(()) => false,
+                Err(TrySendError::Full(_)) => {
+                    info!(account_id, "Supervisor busy, profile change postponed.");
+                    true
+                }
+                Err(TrySendError::Closed(_)) => {
+                    warn!(
+                        account_id,
+                        "Supervisor stopped, profile change not applied."
+                    );
+                    false
+                }
+            }
+        });
+    }
+}
+
+fn validate(profile_name: &str, skill_config: &SkillConfig) -> Result<()> {
+    if !skill_config.skill_type.is_startable() {
+        return Err(AppError::Config(format!(
+            "{profile_name}: {} cannot be started as a skill",
+            skill_config.skill_type
+        )));
+    }
+    if let FilterBy::ItemName(item_name) = &skill_config.filter_by
+        && item_name.trim().is_empty()
+    {
+        return Err(AppError::Config(format!(
+            "{profile_name}: item_name must not be empty"
+        )));
+    }
+    Ok(())
+}
+
+fn changed_fields(previous: &SkillConfig, current: &SkillConfig) -> Vec<String> {
+    let mut changed_fields = vec![];
+    if previous.skill_type != current.skill_type {
+        changed_fields.push(format!(
+            "skill_type {} -> {}",
+            previous.skill_type, current.skill_type
+        ));
+    }
+    if previous.filter_by != current.filter_by {
+        changed_fields.push(format!(
+            "filter_by {:?} -> {:?}",
+            previous.filter_by, current.filter_by
+        ));
+    }
+    if previous.essence_crystal != current.essence_crystal {
+        changed_fields.push(format!(
+            "essence_crystal {} -> {}",
+            previous.essence_crystal, current.essence_crystal
+        ));
+    }
+    if previous.auto_purchase != current.auto_purchase {
+        changed_fields.push(format!(
+            "auto_purchase {} -> {}",
+            previous.auto_purchase, current.auto_purchase
+        ));
+    }
+    changed_fields
+}
+
+/// Watches a profile file and sends every valid edit that changes it. Invalid edits
+/// are logged and skipped, keeping the last valid profiles. Stops once the receiver
+/// is dropped.
+pub fn watch(path: PathBuf, current: Profiles) -> Result<mpsc::Receiver<Profiles>> {
+    // Editors often replace the file instead of writing to it, so watch its directory.
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| AppError::Config(format!("Invalid profile path {}", path.display())))?
+        .to_os_string();
+
+    let (event_sender, mut event_receiver) = mpsc::channel(1);
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        match event {
+            Ok(event) => {
+                // Reading the file reports access events too, which must not trigger a reload.
+                let is_write = event.kind.is_create() || event.kind.is_modify();
+                if is_write
+                    && event
+                        .paths
+                        .iter()
+                        .any(|event_path| event_path.file_name() == Some(&file_name))
+                {
+                    // A full channel already has a reload pending.
+                    event_sender.try_send(()).ok();
+                }
+            }
+            Err(e) => warn!(error = %e, "Profile watcher error."),
+        }
+    })
+    .map_err(|e| AppError::Application(format!("Cannot watch profiles: {e}")))?;
+    watcher
+        .watch(&directory, RecursiveMode::NonRecursive)
+        .map_err(|e| AppError::Application(format!("Cannot watch profiles: {e}")))?;
+    info!(path = %path.display(), "Watching profiles for changes.");
+
+    let (profile_sender, profile_receiver) = mpsc::channel(1);
+    tokio::spawn(async move {
+        // Dropping the watcher stops it, keep it until the task ends.
+        let _watcher = watcher;
+        let mut current = current;
+        while event_receiver.recv().await.is_some() {
+            tokio::time::sleep(SETTLE_DELAY).await;
+            while event_receiver.try_recv().is_ok() {}
+
+            match Profiles::load(&path).await {
+                Ok(profiles) if profiles == current => {
+                    debug!("Profile file touched without changes.");
+                }
+                Ok(profiles) => {
+                    info!(path = %path.display(), "Profiles reloaded.");
+                    current = profiles.clone();
+                    if profile_sender.send(profiles).await.is_err() {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    error!(
+                        path = %path.display(),
+                        error = %e,
+                        "Rejected profile edit, keeping the current profiles."
+                    );
+                }
+            }
+        }
+    });
+    Ok(profile_receiver)
+}
+
+/// Sends each profile update to the supervisors whose configuration it changes. They
+/// apply it when starting their next action; busy ones get it on a later retry. Runs
+/// until `profile_updates` closes.
+pub async fn apply_updates(
+    mut profile_updates: mpsc::Receiver<Profiles>,
+    mut current: Profiles,
+    commands: Vec<(u64, mpsc::Sender<SupervisorCommand>)>,
+    fallback: SkillConfig,
+) {
+    let command_sender = |account_id| {
+        commands
+            .iter()
+            .find(|(id, _)| *id == account_id)
+            .map(|(_, command_sender)| command_sender)
+    };
+    let mut pending_skill_configs = PendingSkillConfigs::default();
+    let mut retry_interval = tokio::time::interval(RETRY_INTERVAL);
+    retry_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            profile_update = profile_updates.recv() => {
+                let Some(profiles) = profile_update else {
+                    return;
+                };
+                let account_ids = commands.iter().map(|(account_id, _)| *account_id);
+                pending_skill_configs.extend(profiles.changes(&current, account_ids, &fallback));
+                current = profiles;
+                pending_skill_configs.send(command_sender);
+            }
+            _ = retry_interval.tick(), if !pending_skill_configs.is_empty() => {
+                pending_skill_configs.send(command_sender);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SkillType;
+
+    fn skill_config(skill_type: SkillType) -> SkillConfig {
+        SkillConfig {
+            skill_type,
+            ..Default::default()
+        }
+    }
+
+    fn profiles(default: Option<SkillType>, accounts: &[(u64, SkillType)]) -> Profiles {
+        Profiles {
+            default: default.map(skill_config),
+            accounts: accounts
+                .iter()
+                .map(|(account_id, skill_type)| (*account_id, skill_config(skill_type.clone())))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn changes_is_empty_for_identical_profiles() {
+        let current = profiles(Some(SkillType::Mining), &[(1, SkillType::Fishing)]);
+        let fallback = skill_config(SkillType::Woodcutting);
+        assert!(
+            current
+                .changes(&current.clone(), [1, 2], &fallback)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn changes_lists_only_accounts_whose_config_changed() {
+        let previous = profiles(None, &[(1, SkillType::Fishing), (2, SkillType::Fishing)]);
+        let current = profiles(None, &[(1, SkillType::Fishing), (2, SkillType::Mining)]);
+        let fallback = skill_config(SkillType::Woodcutting);
+        assert_eq!(
+            current.changes(&previous, [1, 2], &fallback),
+            [(2, skill_config(SkillType::Mining))]
+        );
+    }
+
+    #[test]
+    fn changes_follows_the_default_and_the_fallback() {
+        let previous = profiles(None, &[(1, SkillType::Fishing)]);
+        let current = profiles(Some(SkillType::Mining), &[]);
+        let fallback = skill_config(SkillType::Woodcutting);
+        // Account 1 lost its entry and account 2 no longer uses the fallback, both now
+        // run the default.
+        assert_eq!(
+            current.changes(&previous, [1, 2], &fallback),
+            [
+                (1, skill_config(SkillType::Mining)),
+                (2, skill_config(SkillType::Mining)),
+            ]
+        );
+    }
+
+    #[test]
+    fn pending_skill_configs_keep_changes_for_busy_supervisors() {
+        let (command_sender, mut command_receiver) = mpsc::channel(1);
+        command_sender.try_send(SupervisorCommand::Pause).unwrap();
+        let mut pending_skill_configs = PendingSkillConfigs::default();
+        pending_skill_configs.extend(vec![
+            (1, skill_config(SkillType::Mining)),
+            (2, skill_config(SkillType::Fishing)),
+        ]);
+
+        // Account 2 has no supervisor, its change is dropped.
+        pending_skill_configs.send(|account_id| (account_id == 1).then_some(&command_sender));
+        assert!(!pending_skill_configs.is_empty());
+
+        command_receiver.try_recv().unwrap();
+        pending_skill_configs.send(|account_id| (account_id == 1).then_some(&command_sender));
+        assert!(pending_skill_configs.is_empty());
+        assert!(matches!(
+            command_receiver.try_recv(),
+            Ok(SupervisorCommand::SetSkillConfig(skill_config))
+                if skill_config.skill_type == SkillType::Mining
+        ));
+    }
+}
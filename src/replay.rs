@@ -0,0 +1,458 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    path::Path,
+    sync::Mutex,
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64_STD};
+use once_cell::sync::{Lazy, OnceCell};
+use reqwest::{
+    Request, Response, StatusCode, Url, Version,
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, info};
+
+use crate::{
+    error::{AppError, Result},
+    har::{Har, HarEntry, SENSITIVE_FIELDS},
+    models::Account,
+    transport::response_from_parts,
+};
+
+/// Random values the game expects on every mutating call.
+const OBFUSCATION_FIELDS: [&str; 2] = ["ts2mic5ytx", "qty6bx4peh"];
+
+/// Headers describing the wire encoding of the recorded body, which no longer applies.
+const WIRE_HEADERS: [HeaderName; 3] = [
+    header::CONTENT_LENGTH,
+    header::CONTENT_ENCODING,
+    header::TRANSFER_ENCODING,
+];
+
+/// A hand-written alternative to HAR, e.g. for trimming a recording down to a bug report.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Interaction {
+    pub request: CassetteRequest,
+    pub response: CassetteResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CassetteRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CassetteResponse {
+    pub status: u16,
+    /// Final URL after redirects, when it differs from the request URL.
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RecordingFile {
+    Har(Har),
+    Cassette(Cassette),
+}
+
+/// What a request is matched on: the method, the path and the body without fields
+/// that change on every call or were redacted when recording.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct RequestKey {
+    method: String,
+    path: String,
+    body: String,
+}
+
+impl RequestKey {
+    fn new(method: &str, url: &Url, body: &str) -> Self {
+        Self {
+            method: method.to_uppercase(),
+            path: url.path().trim_end_matches('/').to_string(),
+            body: normalize_body(body),
+        }
+    }
+}
+
+fn is_ignored_field(name: &str) -> bool {
+    OBFUSCATION_FIELDS.contains(&name) || SENSITIVE_FIELDS.contains(&name)
+}
+
+/// Drops ignored fields from a JSON or form body and sorts the rest by name.
+fn normalize_body(body: &str) -> String {
+    if body.is_empty() {
+        return String::new();
+    }
+    if let Ok(Value::Object(json_fields)) = serde_json::from_str::<Value>(body) {
+        let kept_fields: BTreeMap<String, Value> = json_fields
+            .into_iter()
+            .filter(|(name, _)| !is_ignored_field(name))
+            .collect();
+        return serde_json::to_string(&kept_fields).unwrap_or_default();
+    }
+
+    let mut form_fields: Vec<(String, String)> = url::form_urlencoded::parse(body.as_bytes())
+        .into_owned()
+        .filter(|(name, _)| !is_ignored_field(name))
+        .collect();
+    form_fields.sort();
+    url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(form_fields)
+        .finish()
+}
+
+#[derive(Debug, Clone)]
+struct RecordedResponse {
+    status: StatusCode,
+    url: Url,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl RecordedResponse {
+    fn new(
+        status: u16,
+        url: Url,
+        headers: impl IntoIterator<Item = (String, String)>,
+        body: Vec<u8>,
+    ) -> Result<Self> {
+        let status = StatusCode::from_u16(status)
+            .map_err(|e| AppError::Parse(format!("Invalid recorded status: {e}")))?;
+        let headers = headers
+            .into_iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::try_from(name).ok()?,
+                    HeaderValue::try_from(value).ok()?,
+                ))
+            })
+            .filter(|(name, _)| !WIRE_HEADERS.contains(name))
+            .collect();
+        Ok(Self {
+            status,
+            url,
+            headers,
+            body,
+        })
+    }
+}
+
+fn from_har_entry(har_entry: HarEntry) -> Result<(RequestKey, RecordedResponse)> {
+    let request_url = Url::parse(&har_entry.request.url)?;
+    let request_body = har_entry
+        .request
+        .post_data
+        .map(|post_data| post_data.text)
+        .unwrap_or_default();
+    let request_key = RequestKey::new(&har_entry.request.method, &request_url, &request_body);
+
+    let har_response = har_entry.response;
+    let response_url = match har_response.redirect_url.as_str() {
+        "" => request_url,
+        redirect_url => Url::parse(redirect_url)?,
+    };
+    let response_text = har_response.content.text.unwrap_or_default();
+    let response_body = match har_response.content.encoding.as_deref() {
+        Some("base64") => BASE64_STD
+            .decode(response_text)
+            .map_err(|e| AppError::Parse(format!("Invalid base64 response body: {e}")))?,
+        _ => response_text.into_bytes(),
+    };
+    let recorded_response = RecordedResponse::new(
+        har_response.status,
+        response_url,
+        har_response
+            .headers
+            .into_iter()
+            .map(|har_header| (har_header.name, har_header.value)),
+        response_body,
+    )?;
+    Ok((request_key, recorded_response))
+}
+
+fn from_interaction(interaction: Interaction) -> Result<(RequestKey, RecordedResponse)> {
+    let request_url = Url::parse(&interaction.request.url)?;
+    let request_key = RequestKey::new(
+        &interaction.request.method,
+        &request_url,
+        &interaction.request.body,
+    );
+
+    let cassette_response = interaction.response;
+    let response_url = match &cassette_response.url {
+        Some(response_url) => Url::parse(response_url)?,
+        None => request_url,
+    };
+    let recorded_response = RecordedResponse::new(
+        cassette_response.status,
+        response_url,
+        cassette_response.headers,
+        cassette_response.body.into_bytes(),
+    )?;
+    Ok((request_key, recorded_response))
+}
+
+/// Answers every request from a recording instead of the network, once loaded.
+///
+/// Responses to the same request are served in recorded order and the last one is
+/// repeated once they run out, so polling loops keep working.
+#[derive(Default)]
+pub struct Replay {
+    responses: OnceCell<Mutex<HashMap<RequestKey, VecDeque<RecordedResponse>>>>,
+}
+
+pub static REPLAY: Lazy<Replay> = Lazy::new(Replay::default);
+
+impl Replay {
+    /// Loads a HAR file or cassette. Later calls keep the first recording.
+    pub fn load(&self, path: &Path) -> Result<()> {
+        let raw_recording = std::fs::read_to_string(path)?;
+        let exchanges = match serde_json::from_str(&raw_recording)? {
+            RecordingFile::Har(har) => har
+                .log
+                .entries
+                .into_iter()
+                .map(from_har_entry)
+                .collect::<Result<Vec<_>>>()?,
+            RecordingFile::Cassette(cassette) => cassette
+                .interactions
+                .into_iter()
+                .map(from_interaction)
+                .collect::<Result<Vec<_>>>()?,
+        };
+
+        let exchange_count = exchanges.len();
+        let mut responses: HashMap<RequestKey, VecDeque<RecordedResponse>> = HashMap::new();
+        for (request_key, recorded_response) in exchanges {
+            responses
+                .entry(request_key)
+                .or_default()
+                .push_back(recorded_response);
+        }
+        if self.responses.set(Mutex::new(responses)).is_ok() {
+            info!(
+                path = %path.display(),
+                exchanges = exchange_count,
+                "Replaying recorded traffic."
+            );
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.responses.get().is_some()
+    }
+
+    /// Stand-in for the account store, which a replay never reaches.
+    pub fn account(&self) -> Account {
+        Account {
+            id: 0,
+            email: "replay@localhost".to_string(),
+            api_token: "replay".to_string(),
+            cookie_str: String::new(),
+        }
+    }
+
+    pub fn respond(&self, request: &Request) -> Result<Response> {
+        let Some(responses) = self.responses.get() else {
+            return Err(AppError::Replay("no recording loaded".to_string()));
+        };
+        let request_body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(String::from_utf8_lossy)
+            .unwrap_or_default();
+        let request_key = RequestKey::new(request.method().as_str(), request.url(), &request_body);
+
+        let mut responses = responses.lock().unwrap();
+        let recorded_response = match responses.get_mut(&request_key) {
+            Some(queued_responses) if queued_responses.len() > 1 => queued_responses.pop_front(),
+            Some(queued_responses) => queued_responses.front().cloned(),
+            None => None,
+        }
+        .ok_or_else(|| {
+            AppError::Replay(format!(
+                "{} {} {}",
+                request_key.method, request_key.path, request_key.body
+            ))
+        })?;
+
+        debug!(
+            method = %request_key.method,
+            path = %request_key.path,
+            status = %recorded_response.status,
+            "Replayed response."
+        );
+        Ok(response_from_parts(
+            recorded_response.status,
+            Version::HTTP_11,
+            recorded_response.url,
+            recorded_response.headers,
+            recorded_response.body,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::{Client, Method};
+
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    fn interaction(method: &str, url: &str, body: &str, response_body: &str) -> Interaction {
+        Interaction {
+            request: CassetteRequest {
+                method: method.to_string(),
+                url: url.to_string(),
+                body: body.to_string(),
+            },
+            response: CassetteResponse {
+                status: 200,
+                url: None,
+                headers: BTreeMap::from([(
+                    "content-type".to_string(),
+                    "application/json".to_string(),
+                )]),
+                body: response_body.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn ignores_obfuscation_fields_in_json_bodies() {
+        let endpoint = url("https://web.idle-mmo.com/api/action/start");
+        assert_eq!(
+            RequestKey::new(
+                "post",
+                &endpoint,
+                r#"{"character_id":7,"ts2mic5ytx":"a1","qty6bx4peh":"b1"}"#
+            ),
+            RequestKey::new(
+                "POST",
+                &endpoint,
+                r#"{"qty6bx4peh":"b2","character_id":7,"ts2mic5ytx":"a2"}"#
+            )
+        );
+    }
+
+    #[test]
+    fn ignores_obfuscation_and_sensitive_fields_in_form_bodies() {
+        let endpoint = url("https://web.idle-mmo.com/login");
+        assert_eq!(
+            RequestKey::new(
+                "POST",
+                &endpoint,
+                "_token=abc&remember=on&ts2mic5ytx=a1&qty6bx4peh=b1"
+            ),
+            RequestKey::new(
+                "POST",
+                &endpoint,
+                "qty6bx4peh=b2&remember=on&_token=def&ts2mic5ytx=a2"
+            )
+        );
+    }
+
+    #[test]
+    fn ignores_signed_query_strings() {
+        assert_eq!(
+            RequestKey::new(
+                "POST",
+                &url("https://web.idle-mmo.com/api/quick-view/location/3?expires=1&signature=aa"),
+                "",
+            ),
+            RequestKey::new(
+                "POST",
+                &url("https://web.idle-mmo.com/api/quick-view/location/3/?expires=2&signature=bb"),
+                "",
+            )
+        );
+    }
+
+    #[test]
+    fn tells_other_fields_apart() {
+        let endpoint = url("https://web.idle-mmo.com/api/action/start");
+        assert_ne!(
+            RequestKey::new("POST", &endpoint, r#"{"character_id":7}"#),
+            RequestKey::new("POST", &endpoint, r#"{"character_id":8}"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn replays_a_cassette_in_recorded_order() {
+        let cassette = Cassette {
+            interactions: vec![
+                interaction(
+                    "POST",
+                    "https://web.idle-mmo.com/api/action/active?signature=aa",
+                    r#"{"character_id":7,"ts2mic5ytx":"a1"}"#,
+                    r#"{"remaining":10}"#,
+                ),
+                interaction(
+                    "POST",
+                    "https://web.idle-mmo.com/api/action/active?signature=bb",
+                    r#"{"character_id":7,"ts2mic5ytx":"a2"}"#,
+                    r#"{"remaining":0}"#,
+                ),
+            ],
+        };
+        let cassette_path =
+            std::env::temp_dir().join(format!("replay-test-{}.json", std::process::id()));
+        std::fs::write(&cassette_path, serde_json::to_vec(&cassette).unwrap()).unwrap();
+        let replay = Replay::default();
+        let load_result = replay.load(&cassette_path);
+        std::fs::remove_file(&cassette_path).unwrap();
+        load_result.unwrap();
+
+        let request = Client::new()
+            .request(
+                Method::POST,
+                "https://web.idle-mmo.com/api/action/active?signature=cc",
+            )
+            .body(r#"{"ts2mic5ytx":"a3","character_id":7}"#)
+            .build()
+            .unwrap();
+        let mut replayed_bodies = vec![];
+        for _ in 0..3 {
+            let http_response = replay.respond(&request).unwrap();
+            assert_eq!(http_response.status(), StatusCode::OK);
+            assert_eq!(
+                http_response.headers()[header::CONTENT_TYPE],
+                "application/json"
+            );
+            replayed_bodies.push(http_response.text().await.unwrap());
+        }
+        assert_eq!(
+            replayed_bodies,
+            [
+                r#"{"remaining":10}"#,
+                r#"{"remaining":0}"#,
+                r#"{"remaining":0}"#
+            ]
+        );
+
+        let unknown_request = Client::new()
+            .post("https://web.idle-mmo.com/api/action/start")
+            .build()
+            .unwrap();
+        assert!(replay.respond(&unknown_request).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{
+    client::{ActionSkillApi, IdleMMOClient},
+    error::Result,
+    events::{BotEvent, EventEnvelope},
+    models::{CharacterInfo, Metrics, SkillType},
+};
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Json,
+}
+
+/// Character stats and per-skill metrics at one point in a session.
+#[derive(Debug, Clone, Default)]
+pub struct SessionSnapshot {
+    pub at: DateTime<Utc>,
+    pub character_info: CharacterInfo,
+    pub skill_metrics: BTreeMap<SkillType, Metrics>,
+}
+
+impl SessionSnapshot {
+    /// Refreshes the character info and fetches metrics for every skill it has a level in.
+    #[tracing::instrument(skip_all)]
+    pub async fn capture(client: &mut IdleMMOClient) -> Self {
+        if let Err(e) = client.refresh_character_info().await {
+            warn!(error = %e, "Failed to refresh character info, snapshotting the cached copy.");
+        }
+        let character_info = client.cache.character_info.clone();
+        let mut skill_metrics = BTreeMap::new();
+        for skill_type in character_info.skill_level.keys() {
+            match client.get_skill_data(skill_type.clone()).await {
+                Ok(skill_data) => {
+                    skill_metrics.insert(skill_type.clone(), skill_data.metrics);
+                }
+                Err(e) => warn!(%skill_type, error = %e, "Failed to snapshot skill metrics."),
+            }
+        }
+        Self {
+            at: Utc::now(),
+            character_info,
+            skill_metrics,
+        }
+    }
+}
+
+/// What happened during a session that snapshots cannot show.
+#[derive(Debug, Clone, Default)]
+pub struct SessionTally {
+    pub teleport_gold: u64,
+    pub actions_run: BTreeMap<SkillType, u64>,
+}
+
+impl SessionTally {
+    pub fn record(&mut self, event_envelope: &EventEnvelope) {
+        match &event_envelope.event {
+            BotEvent::Teleported { cost, .. } => self.teleport_gold += cost,
+            BotEvent::ActionStarted { skill, .. } => {
+                *self.actions_run.entry(skill.clone()).or_default() += 1;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SkillSummary {
+    pub skill: SkillType,
+    pub experience_gained: u64,
+    pub levels_gained: u64,
+    pub items_produced: u64,
+    pub time_spent_secs: i64,
+    pub actions_run: u64,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SessionReport {
+    pub account_id: u64,
+    pub character_id: u64,
+    pub character: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub combat_levels_gained: u64,
+    pub gold_delta: i64,
+    pub teleport_gold: u64,
+    pub actions_run: u64,
+    pub skills: Vec<SkillSummary>,
+}
+
+impl SessionReport {
+    pub fn new(
+        account_id: u64,
+        start: &SessionSnapshot,
+        end: &SessionSnapshot,
+        tally: &SessionTally,
+    ) -> Self {
+        let start_info = &start.character_info;
+        let end_info = &end.character_info;
+
+        let mut skills: Vec<SkillSummary> = end_info
+            .skill_level
+            .keys()
+            .chain(tally.actions_run.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|skill_type| {
+                let start_metrics = start.skill_metrics.get(skill_type);
+                let end_metrics = end.skill_metrics.get(skill_type);
+                let metric_delta = |metric: fn(&Metrics) -> u64| {
+                    end_metrics
+                        .map(metric)
+                        .unwrap_or_default()
+                        .saturating_sub(start_metrics.map(metric).unwrap_or_default())
+                };
+                let time_spent_delta = end_metrics
+                    .map(|metrics| metrics.time_spent)
+                    .unwrap_or_default()
+                    - start_metrics
+                        .map(|metrics| metrics.time_spent)
+                        .unwrap_or_default();
+                SkillSummary {
+                    skill: skill_type.clone(),
+                    experience_gained: metric_delta(|metrics| metrics.total_experience),
+                    levels_gained: end_info
+                        .skill_level
+                        .get(skill_type)
+                        .copied()
+                        .unwrap_or_default()
+                        .saturating_sub(
+                            start_info
+                                .skill_level
+                                .get(skill_type)
+                                .copied()
+                                .unwrap_or_default(),
+                        ),
+                    items_produced: metric_delta(|metrics| metrics.items_gathered),
+                    time_spent_secs: time_spent_delta.num_seconds().max(0),
+                    actions_run: tally
+                        .actions_run
+                        .get(skill_type)
+                        .copied()
+                        .unwrap_or_default(),
+                }
+            })
+            .collect();
+        skills.retain(|skill_summary| {
+            skill_summary.experience_gained > 0
+                || skill_summary.levels_gained > 0
+                || skill_summary.items_produced > 0
+                || skill_summary.actions_run > 0
+        });
+
+        Self {
+            account_id,
+            character_id: end_info.id,
+            character: end_info.name.clone(),
+            started_at: start.at,
+            ended_at: end.at,
+            combat_levels_gained: end_info
+                .combat_level
+                .saturating_sub(start_info.combat_level),
+            gold_delta: end_info.gold as i64 - start_info.gold as i64,
+            teleport_gold: tally.teleport_gold,
+            actions_run: tally.actions_run.values().sum(),
+            skills,
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let duration = self.ended_at - self.started_at;
+        let mut markdown = format!(
+            "## {} (account {})\n\n\
+             {} → {} ({}h {:02}m)\n\n\
+             - Actions run: {}\n\
+             - Combat levels gained: {}\n\
+             - Gold change: {:+}\n\
+             - Gold spent on teleports: {}\n",
+            self.character,
+            self.account_id,
+            self.started_at.format("%Y-%m-%d %H:%M"),
+            self.ended_at.format("%Y-%m-%d %H:%M"),
+            duration.num_hours(),
+            duration.num_minutes() % 60,
+            self.actions_run,
+            self.combat_levels_gained,
+            self.gold_delta,
+            self.teleport_gold,
+        );
+        if !self.skills.is_empty() {
+            markdown.push_str(
+                "\n| Skill | XP gained | Levels gained | Items produced | Time spent | Actions |\n\
+                 |---|---:|---:|---:|---:|---:|\n",
+            );
+            for skill_summary in &self.skills {
+                markdown.push_str(&format!(
+                    "| {} | {} | {} | {} | {}m | {} |\n",
+                    skill_summary.skill,
+                    skill_summary.experience_gained,
+                    skill_summary.levels_gained,
+                    skill_summary.items_produced,
+                    skill_summary.time_spent_secs / 60,
+                    skill_summary.actions_run,
+                ));
+            }
+        }
+        markdown
+    }
+}
+
+/// Renders the reports of several accounts as one document.
+pub fn render_reports(format: ReportFormat, reports: &[SessionReport]) -> Result<String> {
+    Ok(match format {
+        ReportFormat::Json => serde_json::to_string_pretty(reports)?,
+        ReportFormat::Markdown => {
+            let mut markdown = "# Session summary\n".to_string();
+            for report in reports {
+                markdown.push('\n');
+                markdown.push_str(&report.to_markdown());
+            }
+            markdown
+        }
+    })
+}
@@ -0,0 +1,260 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use tracing::{info, warn};
+
+use crate::{
+    client::{ActionSkillApi, DungeonApi, IdleMMOClient, InventoryApi, LocationApi, MarketApi},
+    crystals::{EssenceCrystalBudget, EssenceCrystalPolicy},
+    error::Result,
+    events::{BotEvent, EVENT_BUS},
+    health::{HealthManager, HealthPolicy},
+    metrics::METRICS,
+    models::{MarketPolicy, SkillConfig, SkillType},
+    travel::TravelPlanner,
+    utils::{find_best_dungeon, find_best_skill},
+};
+
+#[derive(Debug, Clone)]
+pub struct SchedulerOptions {
+    pub skill_config: SkillConfig,
+    pub run_dungeons: bool,
+    pub idle_interval: Duration,
+    pub health_policy: HealthPolicy,
+    pub market_policy: Option<MarketPolicy>,
+    /// When set, replaces `SkillConfig::essence_crystal` with a budgeted amount.
+    pub crystal_policy: Option<EssenceCrystalPolicy>,
+    /// How each supervisor's client travels to its next action.
+    pub travel_planner: TravelPlanner,
+    /// Puts each supervisor's client in dry-run mode.
+    pub dry_run: bool,
+}
+
+impl Default for SchedulerOptions {
+    fn default() -> Self {
+        Self {
+            skill_config: SkillConfig::default(),
+            run_dungeons: false,
+            idle_interval: Duration::from_secs(30),
+            health_policy: HealthPolicy::default(),
+            market_policy: None,
+            crystal_policy: None,
+            travel_planner: TravelPlanner::default(),
+            dry_run: false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    pub options: SchedulerOptions,
+    pub health: HealthManager,
+    pub crystal_budget: Option<EssenceCrystalBudget>,
+    dungeon_pending: bool,
+    /// When dungeons found on cooldown, by id, can be entered again.
+    dungeon_cooldowns: HashMap<u64, DateTime<Utc>>,
+    /// Skill of the action seen running on the previous step.
+    running_skill: Option<SkillType>,
+}
+
+impl Scheduler {
+    pub fn new(options: SchedulerOptions) -> Self {
+        Self {
+            health: HealthManager::new(options.health_policy.clone()),
+            crystal_budget: options
+                .crystal_policy
+                .clone()
+                .map(EssenceCrystalBudget::new),
+            options,
+            dungeon_pending: false,
+            dungeon_cooldowns: HashMap::new(),
+            running_skill: None,
+        }
+    }
+
+    /// Runs one scheduling step and returns how long to wait before the next one.
+    #[tracing::instrument(skip_all)]
+    pub async fn tick(&mut self, client: &mut IdleMMOClient) -> Result<Duration> {
+        if let Some(remaining_time) = self.finish_current(client).await? {
+            return Ok(remaining_time);
+        }
+
+        if self.options.run_dungeons {
+            let now = Utc::now();
+            self.dungeon_cooldowns
+                .retain(|_, cooldown_ends_at| *cooldown_ends_at > now);
+            let mut available_dungeons = client.get_dungeons().await?;
+            available_dungeons.retain(|dungeon| !self.dungeon_cooldowns.contains_key(&dungeon.id));
+            // Cached dungeons have no cooldown, only the one to enter is checked.
+            if let Some(candidate_dungeon) =
+                find_best_dungeon(&available_dungeons, &client.cache.character_info)
+            {
+                let best_dungeon = client.get_dungeon(candidate_dungeon).await?;
+                if best_dungeon.is_on_cooldown() {
+                    info!(
+                        dungeon = %best_dungeon.name,
+                        remaining_secs = best_dungeon.cooldown_remaining().num_seconds(),
+                        "Dungeon on cooldown, skipping it until it ends."
+                    );
+                    self.dungeon_cooldowns
+                        .insert(best_dungeon.id, now + best_dungeon.cooldown_remaining());
+                } else {
+                    if let Some(regeneration_wait) = self.health.ensure_healthy(client).await? {
+                        return Ok(regeneration_wait);
+                    }
+                    info!(dungeon = %best_dungeon.name, "Dungeon off cooldown, entering.");
+                    client.enter_dungeon(&best_dungeon).await?;
+                    self.dungeon_pending = true;
+                    return Ok(self.options.idle_interval);
+                }
+            }
+        }
+
+        if let Some(market_policy) = &self.options.market_policy
+            && let Err(e) = Self::trade(client, &self.options.skill_config, market_policy).await
+        {
+            warn!(error = %e, "Market trading failed, starting skill anyway.");
+        }
+
+        let mut skill_config = self.options.skill_config.clone();
+        if let Some(crystal_budget) = &mut self.crystal_budget {
+            skill_config.essence_crystal =
+                match Self::budget_crystals(client, &skill_config, crystal_budget).await {
+                    Ok(essence_crystals) => essence_crystals,
+                    Err(e) => {
+                        warn!(error = %e, "Failed to budget essence crystals, using none.");
+                        0
+                    }
+                };
+        }
+
+        let essence_crystals = skill_config.essence_crystal;
+        client.start_skill(skill_config).await?;
+        if let Some(crystal_budget) = &mut self.crystal_budget
+            && essence_crystals > 0
+        {
+            crystal_budget.record_spent(essence_crystals);
+            if let Some(character_id) = crystal_budget.character_id
+                && let Err(e) = client
+                    .db_client
+                    .save_crystal_spend(character_id, &crystal_budget.spend())
+                    .await
+            {
+                warn!(error = %e, "Failed to save essence crystal spend.");
+            }
+        }
+        Ok(self.options.idle_interval)
+    }
+
+    /// Follows the running action without starting anything new: records it once it has
+    /// finished and collects dungeon rewards. Returns the time left while it still runs.
+    #[tracing::instrument(skip_all)]
+    pub async fn finish_current(&mut self, client: &mut IdleMMOClient) -> Result<Option<Duration>> {
+        if let Some(active_action) = client.get_active_action().await? {
+            if active_action.skill_type == SkillType::Dungeon {
+                self.dungeon_pending = true;
+            }
+            if active_action.skill_type != SkillType::Travelling {
+                self.running_skill = Some(active_action.skill_type.clone());
+            }
+            let remaining_time = active_action
+                .expires_in
+                .to_std()
+                .unwrap_or(self.options.idle_interval);
+            return Ok(Some(remaining_time.max(Duration::from_secs(1))));
+        }
+
+        if let Some(finished_skill) = self.running_skill.take() {
+            let is_dungeon = finished_skill == SkillType::Dungeon;
+            Self::record_completion(client, finished_skill).await;
+            // Picks up level ups and gold from the action. Collecting dungeon rewards
+            // below refreshes the character itself.
+            if !is_dungeon && let Err(e) = client.update_current_data().await {
+                warn!(error = %e, "Failed to refresh character after the action.");
+            }
+        }
+
+        if self.dungeon_pending {
+            match client.collect_dungeon_rewards().await {
+                Ok(_) => self.dungeon_pending = false,
+                Err(e) => warn!(error = %e, "Failed to collect dungeon rewards."),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Forgets the action seen on the previous step, e.g. after switching characters.
+    pub fn forget_running_action(&mut self) {
+        self.running_skill = None;
+    }
+
+    /// Counts the finished action and refreshes its skill's experience metrics.
+    async fn record_completion(client: &IdleMMOClient, finished_skill: SkillType) {
+        METRICS.record_action_completed(&client.cache.character_info.name, &finished_skill);
+        EVENT_BUS.publish(BotEvent::ActionCompleted {
+            character: client.cache.character_info.name.clone(),
+            skill: finished_skill.clone(),
+        });
+        if finished_skill != SkillType::Dungeon
+            && let Err(e) = client.get_skill_data(finished_skill).await
+        {
+            warn!(error = %e, "Failed to refresh skill metrics.");
+        }
+    }
+
+    /// Reads the owned crystal count and asks the budget how many the next skill item gets.
+    /// The spend of a character new to the budget is restored from the store first.
+    async fn budget_crystals(
+        client: &mut IdleMMOClient,
+        skill_config: &SkillConfig,
+        crystal_budget: &mut EssenceCrystalBudget,
+    ) -> Result<u64> {
+        let character_id = client.cache.character_info.id;
+        if crystal_budget.character_id != Some(character_id) {
+            let spend = client.db_client.load_crystal_spend(character_id).await?;
+            crystal_budget.restore(character_id, spend);
+        }
+        let owned_crystals = client.get_essence_crystal_count().await?;
+        let available_locations = client.get_locations(true).await?;
+        let essence_crystals = find_best_skill(&available_locations, skill_config)
+            .map(|(_, selected_skill_item)| {
+                crystal_budget.allowance(selected_skill_item, owned_crystals)
+            })
+            .unwrap_or_default();
+        info!(
+            owned_crystals,
+            essence_crystals, "Essence crystals budgeted."
+        );
+        Ok(essence_crystals)
+    }
+
+    /// Sells surplus items and buys whatever the next skill item is missing.
+    async fn trade(
+        client: &mut IdleMMOClient,
+        skill_config: &SkillConfig,
+        market_policy: &MarketPolicy,
+    ) -> Result<()> {
+        client.sell_surplus(market_policy).await?;
+
+        let available_locations = client.get_locations(true).await?;
+        if let Some((_, selected_skill_item)) = find_best_skill(&available_locations, skill_config)
+        {
+            client
+                .buy_missing_inputs(selected_skill_item, 1, market_policy)
+                .await?;
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn run(&mut self, client: &mut IdleMMOClient) -> Result<()> {
+        loop {
+            let wait_duration = self.tick(client).await?;
+            info!(
+                wait_secs = wait_duration.as_secs(),
+                "Waiting for next action."
+            );
+            tokio::time::sleep(wait_duration).await;
+        }
+    }
+}
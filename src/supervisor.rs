@@ -0,0 +1,526 @@
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{
+        broadcast::{self, error::TryRecvError},
+        mpsc, watch,
+    },
+    task::JoinHandle,
+    time::{Instant, MissedTickBehavior},
+};
+use tracing::{Instrument, error, info, info_span, warn};
+
+use crate::{
+    client::{AccountManagement, ActionSkillApi, CharacterApi, IdleMMOClient},
+    error::Result,
+    events::{BotEvent, EVENT_BUS, EventEnvelope},
+    metrics::METRICS,
+    models::{Account, Action, CharacterInfo, FilterBy, SkillConfig, SkillType},
+    profile::Profiles,
+    report::{SessionReport, SessionSnapshot, SessionTally},
+    scheduler::{Scheduler, SchedulerOptions},
+    utils::obfuscate_email,
+};
+
+/// How long supervisors get to finish their current step when shutting down.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often the progress of a running action is published while waiting for it.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(30);
+
+tokio::task_local! {
+    /// Account the current supervisor task runs for.
+    static ACCOUNT_ID: u64;
+}
+
+/// Account of the supervisor running the current task, if any.
+pub fn current_account_id() -> Option<u64> {
+    ACCOUNT_ID.try_with(|account_id| *account_id).ok()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum SupervisorCommand {
+    /// Lets the running action finish, then idles until resumed.
+    Pause,
+    Resume,
+    SetFilterBy(FilterBy),
+    /// Replaces the whole skill profile from the next scheduling step on.
+    SetSkillConfig(SkillConfig),
+    /// Reloads the account from the store and loads its session again.
+    Relogin,
+    /// Switches to another character, by id or name.
+    SwitchCharacter(String),
+    /// Saves the session and exits once the current step has finished.
+    Shutdown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SupervisorStatus {
+    #[default]
+    Starting,
+    Running,
+    Paused,
+    Failed,
+    Stopped,
+}
+
+/// Snapshot of an account published after every scheduling step.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct AccountState {
+    pub account_id: u64,
+    pub email: String,
+    pub status: SupervisorStatus,
+    pub character_info: CharacterInfo,
+    pub location_name: String,
+    pub active_action: Option<Action>,
+    pub observed_at: Option<DateTime<Utc>>,
+    pub skill: SkillType,
+    pub filter_by: FilterBy,
+    pub last_error: Option<String>,
+    /// One per character played, set once the supervisor has shut down gracefully.
+    pub session_reports: Vec<SessionReport>,
+}
+
+impl AccountState {
+    /// Time left on the active action, counted down from when it was observed.
+    pub fn remaining_time(&self) -> Option<TimeDelta> {
+        let active_action = self.active_action.as_ref()?;
+        let elapsed_time = Utc::now() - self.observed_at?;
+        Some((active_action.expires_in - elapsed_time).max(TimeDelta::zero()))
+    }
+}
+
+#[derive(Debug)]
+pub struct SupervisorHandle {
+    pub account_id: u64,
+    pub commands: mpsc::Sender<SupervisorCommand>,
+    pub state: watch::Receiver<AccountState>,
+    pub task: JoinHandle<()>,
+}
+
+impl SupervisorHandle {
+    /// Last published state, marked stopped if the task ended without saying so, e.g.
+    /// because it was aborted.
+    pub fn current_state(&self) -> AccountState {
+        let mut account_state = self.state.borrow().clone();
+        if self.task.is_finished()
+            && !matches!(
+                account_state.status,
+                SupervisorStatus::Stopped | SupervisorStatus::Failed
+            )
+        {
+            account_state.status = SupervisorStatus::Stopped;
+        }
+        account_state
+    }
+}
+
+pub struct Supervisor {
+    account: Account,
+    client: IdleMMOClient,
+    scheduler: Scheduler,
+    commands: mpsc::Receiver<SupervisorCommand>,
+    state: watch::Sender<AccountState>,
+    paused: bool,
+    consecutive_failures: u32,
+    events: broadcast::Receiver<EventEnvelope>,
+    session_start: SessionSnapshot,
+    session_tally: SessionTally,
+    /// Reports of the characters played before the current one.
+    session_reports: Vec<SessionReport>,
+}
+
+impl Supervisor {
+    pub fn spawn(account: Account, options: SchedulerOptions) -> Result<SupervisorHandle> {
+        let mut client = IdleMMOClient::new()?;
+        client.set_dry_run(options.dry_run);
+        client.travel_planner = options.travel_planner.clone();
+        let (command_sender, command_receiver) = mpsc::channel(16);
+        let (state_sender, state_receiver) = watch::channel(AccountState {
+            account_id: account.id,
+            email: obfuscate_email(&account.email),
+            skill: options.skill_config.skill_type.clone(),
+            filter_by: options.skill_config.filter_by.clone(),
+            ..Default::default()
+        });
+
+        let account_id = account.id;
+        let supervisor = Self {
+            account,
+            client,
+            scheduler: Scheduler::new(options),
+            commands: command_receiver,
+            state: state_sender,
+            paused: false,
+            consecutive_failures: 0,
+            events: EVENT_BUS.subscribe(),
+            session_start: SessionSnapshot::default(),
+            session_tally: SessionTally::default(),
+            session_reports: Vec::new(),
+        };
+        let task = tokio::spawn(
+            ACCOUNT_ID
+                .scope(account_id, supervisor.run())
+                .instrument(info_span!("supervisor", account_id)),
+        );
+
+        Ok(SupervisorHandle {
+            account_id,
+            commands: command_sender,
+            state: state_receiver,
+            task,
+        })
+    }
+
+    async fn run(mut self) {
+        if !self.load().await {
+            return;
+        }
+        self.session_start = SessionSnapshot::capture(&mut self.client).await;
+
+        let mut progress_interval =
+            tokio::time::interval_at(Instant::now() + PROGRESS_INTERVAL, PROGRESS_INTERVAL);
+        progress_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            let mut last_error = None;
+            let step_result = if self.paused {
+                self.scheduler
+                    .finish_current(&mut self.client)
+                    .await
+                    .map(|remaining_time| {
+                        remaining_time.unwrap_or(self.scheduler.options.idle_interval)
+                    })
+            } else {
+                self.scheduler.tick(&mut self.client).await
+            };
+            let wait_duration = match step_result {
+                Ok(wait_duration) => {
+                    self.consecutive_failures = 0;
+                    wait_duration
+                }
+                Err(e) => {
+                    warn!(error = %e, "Scheduling step failed.");
+                    METRICS.record_retry("scheduler_tick");
+                    self.consecutive_failures += 1;
+                    EVENT_BUS.publish(BotEvent::ActionFailed {
+                        error: e.to_string(),
+                        consecutive_failures: self.consecutive_failures,
+                    });
+                    last_error = Some(e.to_string());
+                    self.scheduler.options.idle_interval
+                }
+            };
+            self.publish_state(last_error.clone()).await;
+            self.tally_events();
+            progress_interval.reset();
+
+            let step_wait = tokio::time::sleep(wait_duration);
+            tokio::pin!(step_wait);
+            loop {
+                tokio::select! {
+                    received_command = self.commands.recv() => {
+                        match received_command {
+                            Some(SupervisorCommand::Shutdown) => {
+                                self.shutdown().await;
+                                return;
+                            }
+                            Some(SupervisorCommand::Relogin) => {
+                                if !self.relogin().await {
+                                    return;
+                                }
+                            }
+                            Some(SupervisorCommand::SwitchCharacter(character)) => {
+                                self.switch_character(&character).await;
+                            }
+                            Some(command) => self.handle_command(command),
+                            None => {
+                                info!("Command channel closed, stopping supervisor.");
+                                return;
+                            }
+                        }
+                        break;
+                    }
+                    _ = &mut step_wait => break,
+                    _ = progress_interval.tick() => {
+                        if self.state.borrow().active_action.is_some() {
+                            self.publish_state(last_error.clone()).await;
+                            self.tally_events();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Loads the account's session. Returns `false` once the supervisor has failed.
+    async fn load(&mut self) -> bool {
+        let load_result = self.client.load_account(self.account.clone()).await;
+        self.loaded(load_result)
+    }
+
+    fn loaded(&mut self, load_result: Result<bool>) -> bool {
+        match load_result {
+            Ok(true) => {
+                Self::publish_status(self.status(), None);
+                true
+            }
+            Ok(false) => {
+                self.fail("Session expired".to_string());
+                false
+            }
+            Err(e) => {
+                self.fail(e.to_string());
+                false
+            }
+        }
+    }
+
+    /// Picks up a session stored since the supervisor started, e.g. by `accounts add`.
+    async fn relogin(&mut self) -> bool {
+        info!("Logging in again.");
+        match self.client.get_account().await {
+            Ok(stored_accounts) => {
+                if let Some(stored_account) = stored_accounts
+                    .into_iter()
+                    .find(|stored_account| stored_account.id == self.account.id)
+                {
+                    self.account = stored_account;
+                }
+            }
+            Err(e) => warn!(error = %e, "Failed to reload account, reusing stored session."),
+        }
+        // The account stays stored when this session expired too, so it can be logged in again.
+        let resume_result = self.client.resume_session(self.account.clone()).await;
+        self.loaded(resume_result)
+    }
+
+    async fn switch_character(&mut self, selector: &str) {
+        info!(character = selector, "Switching character.");
+        let switch_result = match self.client.find_character(selector).await {
+            Ok(target_character) => {
+                // The current character can only be snapshotted before switching away.
+                self.tally_events();
+                let session_end = SessionSnapshot::capture(&mut self.client).await;
+                self.client
+                    .switch_character(target_character)
+                    .await
+                    .map(|()| session_end)
+            }
+            Err(e) => Err(e),
+        };
+        match switch_result {
+            Ok(session_end) => {
+                self.scheduler.forget_running_action();
+                self.session_reports.push(SessionReport::new(
+                    self.account.id,
+                    &self.session_start,
+                    &session_end,
+                    &self.session_tally,
+                ));
+                self.session_start = SessionSnapshot::capture(&mut self.client).await;
+                self.session_tally = SessionTally::default();
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to switch character.");
+                self.state.send_modify(|account_state| {
+                    account_state.last_error = Some(e.to_string());
+                });
+            }
+        }
+    }
+
+    fn handle_command(&mut self, command: SupervisorCommand) {
+        info!(?command, "Supervisor command received.");
+        let previous_status = self.status();
+        match command {
+            SupervisorCommand::Pause => self.paused = true,
+            SupervisorCommand::Resume => self.paused = false,
+            SupervisorCommand::SetFilterBy(filter_by) => {
+                self.scheduler.options.skill_config.filter_by = filter_by;
+            }
+            SupervisorCommand::SetSkillConfig(skill_config) => {
+                self.scheduler.options.skill_config = skill_config;
+            }
+            SupervisorCommand::Relogin
+            | SupervisorCommand::SwitchCharacter(_)
+            | SupervisorCommand::Shutdown => {}
+        }
+        if self.status() != previous_status {
+            Self::publish_status(self.status(), None);
+        }
+        self.state.send_modify(|account_state| {
+            account_state.status = self.status();
+            account_state.skill = self.scheduler.options.skill_config.skill_type.clone();
+            account_state.filter_by = self.scheduler.options.skill_config.filter_by.clone();
+        });
+    }
+
+    fn publish_status(status: SupervisorStatus, reason: Option<String>) {
+        EVENT_BUS.publish(BotEvent::SupervisorStatusChanged { status, reason });
+    }
+
+    fn status(&self) -> SupervisorStatus {
+        if self.paused {
+            SupervisorStatus::Paused
+        } else {
+            SupervisorStatus::Running
+        }
+    }
+
+    async fn publish_state(&mut self, last_error: Option<String>) {
+        let active_action = match self.client.get_active_action().await {
+            Ok(active_action) => active_action,
+            Err(e) => {
+                warn!(error = %e, "Failed to refresh active action.");
+                None
+            }
+        };
+        let character_info = self.client.cache.character_info.clone();
+        if let Some(active_action) = &active_action {
+            EVENT_BUS.publish(BotEvent::ActionProgress {
+                character: character_info.name.clone(),
+                skill: active_action.skill_type.clone(),
+                item: active_action.item_name.clone(),
+                progress: active_action.current_progress,
+                quantity: active_action.quantity,
+                max_quantity: active_action.max_quantity,
+                remaining_secs: active_action.expires_in.num_seconds(),
+            });
+        }
+        let location_name = self
+            .client
+            .cache
+            .locations
+            .iter()
+            .find(|location| location.id == character_info.location_id)
+            .map(|location| location.name.clone())
+            .unwrap_or_default();
+
+        let status = self.status();
+        self.state.send_modify(|account_state| {
+            account_state.status = status;
+            account_state.character_info = character_info;
+            account_state.location_name = location_name;
+            account_state.active_action = active_action;
+            account_state.observed_at = Some(Utc::now());
+            account_state.last_error = last_error;
+        });
+    }
+
+    /// Counts this account's events since the last step into the session tally.
+    fn tally_events(&mut self) {
+        loop {
+            match self.events.try_recv() {
+                Ok(event_envelope) if event_envelope.account_id == Some(self.account.id) => {
+                    self.session_tally.record(&event_envelope);
+                }
+                Ok(_) => {}
+                Err(TryRecvError::Lagged(skipped_events)) => {
+                    warn!(skipped_events, "Session tally fell behind, events skipped.");
+                }
+                Err(_) => return,
+            }
+        }
+    }
+
+    async fn shutdown(&mut self) {
+        info!("Shutting down supervisor.");
+        self.tally_events();
+        let session_end = SessionSnapshot::capture(&mut self.client).await;
+        self.session_reports.push(SessionReport::new(
+            self.account.id,
+            &self.session_start,
+            &session_end,
+            &self.session_tally,
+        ));
+        let session_reports = std::mem::take(&mut self.session_reports);
+
+        let last_error = match self.client.save_session(self.account.id).await {
+            Ok(()) => None,
+            Err(e) => {
+                warn!(error = %e, "Failed to save session cookies.");
+                Some(e.to_string())
+            }
+        };
+        Self::publish_status(SupervisorStatus::Stopped, last_error.clone());
+        self.state.send_modify(|account_state| {
+            account_state.status = SupervisorStatus::Stopped;
+            account_state.last_error = last_error;
+            account_state.session_reports = session_reports;
+        });
+    }
+
+    fn fail(&mut self, reason: String) {
+        error!(%reason, "Supervisor stopped.");
+        Self::publish_status(SupervisorStatus::Failed, Some(reason.clone()));
+        self.state.send_modify(|account_state| {
+            account_state.status = SupervisorStatus::Failed;
+            account_state.last_error = Some(reason);
+        });
+    }
+}
+
+/// Spawns a supervisor per account, each with its skill from `profiles`.
+pub fn spawn_all(
+    accounts: Vec<Account>,
+    options: &SchedulerOptions,
+    profiles: &Profiles,
+) -> Result<Vec<SupervisorHandle>> {
+    accounts
+        .into_iter()
+        .map(|account| {
+            let account_options = profiles.scheduler_options(account.id, options);
+            Supervisor::spawn(account, account_options)
+        })
+        .collect()
+}
+
+pub async fn wait_all(handles: &mut [SupervisorHandle]) {
+    for handle in handles {
+        if let Err(e) = (&mut handle.task).await {
+            error!(account_id = handle.account_id, error = %e, "Supervisor task panicked.");
+        }
+    }
+}
+
+/// Asks every supervisor to save its session and report, waits up to `timeout` for each
+/// and returns their final state.
+pub async fn shutdown_all(
+    mut handles: Vec<SupervisorHandle>,
+    timeout: Duration,
+) -> Vec<AccountState> {
+    info!(supervisors = handles.len(), "Shutting down supervisors.");
+    for handle in &handles {
+        handle.commands.send(SupervisorCommand::Shutdown).await.ok();
+    }
+
+    for handle in &mut handles {
+        // Finished tasks may already have been awaited and must not be polled again.
+        if handle.task.is_finished() {
+            continue;
+        }
+        match tokio::time::timeout(timeout, &mut handle.task).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                error!(account_id = handle.account_id, error = %e, "Supervisor task panicked.");
+            }
+            Err(_) => {
+                warn!(
+                    account_id = handle.account_id,
+                    "Supervisor did not stop in time, aborting."
+                );
+                handle.task.abort();
+            }
+        }
+    }
+
+    let mut final_states: Vec<AccountState> = handles
+        .iter()
+        .map(SupervisorHandle::current_state)
+        .collect();
+    final_states.sort_by_key(|account_state| account_state.account_id);
+    final_states
+}
@@ -0,0 +1,226 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::{
+    Body, Request, RequestBuilder, Response, ResponseBuilderExt, StatusCode, Url, Version,
+    cookie::{CookieStore, Jar},
+    header::{self, HeaderMap, HeaderValue},
+};
+
+use crate::{
+    error::Result,
+    har::{HAR_RECORDER, HarEntry, HarRequest, HarResponse, redact_text},
+    metrics::{METRICS, account_label, endpoint_label},
+    replay::REPLAY,
+};
+
+/// Requests per second a client sends when fanning out, e.g. for location details.
+const DEFAULT_REQUESTS_PER_SECOND: u32 = 5;
+
+/// Spaces requests at least an interval apart, across every task sharing it.
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<tokio::time::Instant>,
+}
+
+impl RateLimiter {
+    pub fn per_second(requests: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / requests.max(1),
+            next_slot: Mutex::new(tokio::time::Instant::now()),
+        }
+    }
+
+    /// Waits for the next free slot.
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let slot = (*next_slot).max(tokio::time::Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::per_second(DEFAULT_REQUESTS_PER_SECOND)
+    }
+}
+
+/// Headers and cookies a client adds to every request, which a built request does not show.
+#[derive(Debug, Clone, Default)]
+pub struct RequestDefaults {
+    headers: HeaderMap,
+    jar: Arc<Jar>,
+}
+
+impl RequestDefaults {
+    pub fn new(headers: HeaderMap, jar: Arc<Jar>) -> Self {
+        Self { headers, jar }
+    }
+
+    /// Adds what the client sends along with `request`, keeping headers the request sets itself.
+    fn apply(&self, request: &mut Request) {
+        let cookies = self.jar.cookies(request.url());
+        let request_headers = request.headers_mut();
+        for (name, value) in &self.headers {
+            if !request_headers.contains_key(name) {
+                request_headers.insert(name, value.clone());
+            }
+        }
+        if let Some(cookies) = cookies
+            && !request_headers.contains_key(header::COOKIE)
+        {
+            request_headers.insert(header::COOKIE, cookies);
+        }
+    }
+}
+
+/// Sends a request, counts it by endpoint and status and records it when HAR recording is on.
+/// While a recording is being replayed, the response comes from it instead of the network.
+#[async_trait]
+pub trait ObservedSend {
+    /// `request_defaults` must be those of the client the request was built from.
+    async fn send_observed(self, request_defaults: &RequestDefaults) -> Result<Response>;
+}
+
+#[async_trait]
+impl ObservedSend for RequestBuilder {
+    async fn send_observed(self, request_defaults: &RequestDefaults) -> Result<Response> {
+        let request = self
+            .try_clone()
+            .and_then(|request_builder| request_builder.build().ok());
+        let endpoint = request
+            .as_ref()
+            .map(|request| {
+                format!(
+                    "{} {}",
+                    request.method(),
+                    endpoint_label(request.url().path())
+                )
+            })
+            .unwrap_or_default();
+
+        if REPLAY.is_enabled() {
+            let replay_result = match &request {
+                Some(request) => REPLAY.respond(request),
+                None => REPLAY.respond(&self.build()?),
+            };
+            let status = match &replay_result {
+                Ok(http_response) => http_response.status().as_u16().to_string(),
+                Err(_) => "replay_miss".to_string(),
+            };
+            METRICS.record_request(&endpoint, &status);
+            return replay_result;
+        }
+
+        let started_at = Utc::now();
+        let timer = Instant::now();
+        let send_result = self.send().await;
+        let status = match &send_result {
+            Ok(http_response) => http_response.status().as_u16().to_string(),
+            Err(_) => "error".to_string(),
+        };
+        METRICS.record_request(&endpoint, &status);
+
+        match (send_result?, request) {
+            (http_response, Some(mut request)) if HAR_RECORDER.is_enabled() => {
+                request_defaults.apply(&mut request);
+                record_exchange(&request, http_response, started_at, timer).await
+            }
+            (http_response, _) => Ok(http_response),
+        }
+    }
+}
+
+/// Builds a response that behaves like one received from `url`.
+pub fn response_from_parts(
+    status: StatusCode,
+    version: Version,
+    url: Url,
+    headers: HeaderMap,
+    body: impl Into<Body>,
+) -> Response {
+    let mut response_builder = http::Response::builder()
+        .status(status)
+        .version(version)
+        .url(url);
+    if let Some(response_headers) = response_builder.headers_mut() {
+        *response_headers = headers;
+    }
+    let http_response = response_builder
+        .body(body)
+        .expect("status and version are already valid");
+    Response::from(http_response)
+}
+
+/// Reads the whole response for the recorder and hands back an equivalent one.
+async fn record_exchange(
+    request: &Request,
+    http_response: Response,
+    started_at: DateTime<Utc>,
+    timer: Instant,
+) -> Result<Response> {
+    let wait = timer.elapsed();
+    let status = http_response.status();
+    let version = http_response.version();
+    let headers = http_response.headers().clone();
+    let url = http_response.url().clone();
+    let body = http_response.bytes().await?;
+
+    let mut har_response = HarResponse::new(status, version, &headers, &body);
+    // Redirects were already followed, keep where they ended for replays.
+    if url != *request.url() {
+        har_response.redirect_url = redact_text(url.as_str());
+    }
+    let har_entry = HarEntry::new(
+        started_at,
+        wait,
+        timer.elapsed(),
+        HarRequest::from_request(request),
+        har_response,
+    );
+    HAR_RECORDER.record(&account_label(), har_entry).await;
+
+    Ok(response_from_parts(status, version, url, headers, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Client;
+
+    use super::*;
+
+    #[test]
+    fn request_defaults_add_client_headers_and_jar_cookies() {
+        let url = Url::parse("https://web.idle-mmo.com/").unwrap();
+        let jar = Arc::new(Jar::default());
+        jar.add_cookie_str("idlemmo_session=abc", &url);
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer xyz"),
+        );
+        default_headers.insert(header::REFERER, HeaderValue::from_static("https://a.test/"));
+        let request_defaults = RequestDefaults::new(default_headers, jar);
+
+        let mut request = Client::new()
+            .get(url)
+            .header(header::REFERER, "https://b.test/")
+            .build()
+            .unwrap();
+        request_defaults.apply(&mut request);
+
+        let headers = request.headers();
+        assert_eq!(headers[header::AUTHORIZATION], "Bearer xyz");
+        assert_eq!(headers[header::REFERER], "https://b.test/");
+        assert_eq!(headers[header::COOKIE], "idlemmo_session=abc");
+    }
+}
@@ -0,0 +1,194 @@
+use std::time::Duration;
+
+use tracing::debug;
+
+use crate::{
+    error::{AppError, Result},
+    models::{CharacterInfo, location::WorldLocation},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TravelPlan {
+    Stay,
+    Walk { duration: Duration },
+    Teleport { cost: u64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct TravelPlanner {
+    /// Gold that must remain after paying for a teleport.
+    pub gold_floor: u64,
+    /// Walking time per unit of `WorldLocation::distance`.
+    pub walk_time_per_distance: Duration,
+    /// Walk instead of teleporting while the walk takes at most this fraction
+    /// of the upcoming action's duration.
+    pub max_walk_ratio: f64,
+    /// Longest walk accepted when a teleport is not affordable.
+    pub max_walk_duration: Duration,
+}
+
+impl Default for TravelPlanner {
+    fn default() -> Self {
+        Self {
+            gold_floor: 0,
+            walk_time_per_distance: Duration::from_secs(1),
+            max_walk_ratio: 0.1,
+            max_walk_duration: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+impl TravelPlanner {
+    pub fn walk_duration(&self, destination: &WorldLocation) -> Duration {
+        self.walk_time_per_distance
+            .saturating_mul(destination.distance.try_into().unwrap_or(u32::MAX))
+    }
+
+    pub fn can_afford_teleport(
+        &self,
+        character_info: &CharacterInfo,
+        destination: &WorldLocation,
+    ) -> bool {
+        character_info.gold >= destination.teleport_cost.saturating_add(self.gold_floor)
+    }
+
+    /// Picks how to reach `destination` before running an action lasting `upcoming_action`.
+    pub fn plan(
+        &self,
+        character_info: &CharacterInfo,
+        destination: &WorldLocation,
+        upcoming_action: Duration,
+    ) -> Result<TravelPlan> {
+        if character_info.location_id == destination.id {
+            return Ok(TravelPlan::Stay);
+        }
+
+        let walk_duration = self.walk_duration(destination);
+        let can_teleport = self.can_afford_teleport(character_info, destination);
+        let walk_is_cheap =
+            walk_duration.as_secs_f64() <= upcoming_action.as_secs_f64() * self.max_walk_ratio;
+        debug!(
+            walk_secs = walk_duration.as_secs(),
+            upcoming_secs = upcoming_action.as_secs(),
+            teleport_cost = destination.teleport_cost,
+            can_teleport,
+            walk_is_cheap,
+            "Planning travel."
+        );
+
+        if can_teleport && !walk_is_cheap {
+            Ok(TravelPlan::Teleport {
+                cost: destination.teleport_cost,
+            })
+        } else if walk_is_cheap || walk_duration <= self.max_walk_duration {
+            Ok(TravelPlan::Walk {
+                duration: walk_duration,
+            })
+        } else {
+            Err(AppError::Travel(format!(
+                "{} is unreachable: teleport costs {} (gold {}, floor {}) and walking takes {}s",
+                destination.name,
+                destination.teleport_cost,
+                character_info.gold,
+                self.gold_floor,
+                walk_duration.as_secs()
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn planner() -> TravelPlanner {
+        TravelPlanner {
+            gold_floor: 100,
+            walk_time_per_distance: Duration::from_secs(1),
+            max_walk_ratio: 0.1,
+            max_walk_duration: 10 * MINUTE,
+        }
+    }
+
+    fn character(gold: u64) -> CharacterInfo {
+        CharacterInfo {
+            gold,
+            location_id: 1,
+            ..Default::default()
+        }
+    }
+
+    fn destination(distance: u64, teleport_cost: u64) -> WorldLocation {
+        WorldLocation {
+            id: 2,
+            name: "Destination".to_string(),
+            distance,
+            teleport_cost,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn stays_at_the_current_location() {
+        let current_location = WorldLocation {
+            id: 1,
+            ..destination(600, 50)
+        };
+        assert_eq!(
+            planner()
+                .plan(&character(0), &current_location, MINUTE)
+                .unwrap(),
+            TravelPlan::Stay
+        );
+    }
+
+    #[test]
+    fn walks_when_the_walk_is_cheap_even_if_teleport_is_affordable() {
+        assert_eq!(
+            planner()
+                .plan(&character(1_000), &destination(60, 50), 10 * MINUTE)
+                .unwrap(),
+            TravelPlan::Walk { duration: MINUTE }
+        );
+    }
+
+    #[test]
+    fn teleports_when_affordable_and_the_walk_is_not_cheap() {
+        assert_eq!(
+            planner()
+                .plan(&character(1_000), &destination(120, 50), 10 * MINUTE)
+                .unwrap(),
+            TravelPlan::Teleport { cost: 50 }
+        );
+    }
+
+    #[test]
+    fn teleports_when_exactly_the_gold_floor_remains() {
+        assert_eq!(
+            planner()
+                .plan(&character(150), &destination(120, 50), 10 * MINUTE)
+                .unwrap(),
+            TravelPlan::Teleport { cost: 50 }
+        );
+    }
+
+    #[test]
+    fn walks_within_the_limit_when_a_teleport_would_break_the_gold_floor() {
+        assert_eq!(
+            planner()
+                .plan(&character(149), &destination(120, 50), 10 * MINUTE)
+                .unwrap(),
+            TravelPlan::Walk {
+                duration: 2 * MINUTE
+            }
+        );
+    }
+
+    #[test]
+    fn fails_when_unaffordable_and_beyond_the_walk_limit() {
+        let plan_result = planner().plan(&character(149), &destination(601, 50), MINUTE);
+        assert!(matches!(plan_result, Err(AppError::Travel(_))));
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    collections::VecDeque,
+    io::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, Paragraph, Row, Table, TableState},
+};
+use tracing::info;
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::{
+    error::{AppError, Result},
+    models::FilterBy,
+    supervisor::{AccountState, SupervisorCommand, SupervisorHandle},
+};
+
+const LOG_CAPACITY: usize = 1000;
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+/// Ring buffer of formatted log lines, used as the tracing writer in dashboard mode.
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer(Arc<Mutex<VecDeque<String>>>);
+
+impl LogBuffer {
+    fn lines(&self) -> Vec<String> {
+        self.0.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+pub struct LogWriter(LogBuffer);
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut log_lines = (self.0).0.lock().unwrap();
+        for line in String::from_utf8_lossy(buf).lines() {
+            if log_lines.len() == LOG_CAPACITY {
+                log_lines.pop_front();
+            }
+            log_lines.push_back(line.to_string());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogBuffer {
+    type Writer = LogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LogWriter(self.clone())
+    }
+}
+
+struct Dashboard {
+    handles: Vec<SupervisorHandle>,
+    log_buffer: LogBuffer,
+    table_state: TableState,
+    /// Lines scrolled up from the bottom of the event log.
+    log_scroll: usize,
+}
+
+impl Dashboard {
+    fn selected_handle(&self) -> Option<&SupervisorHandle> {
+        self.handles.get(self.table_state.selected()?)
+    }
+
+    fn send(&self, command: SupervisorCommand) {
+        if let Some(handle) = self.selected_handle() {
+            handle.commands.try_send(command).ok();
+        }
+    }
+
+    fn cycle_strategy(&self) {
+        let Some(handle) = self.selected_handle() else {
+            return;
+        };
+        let next_filter_by = match &handle.state.borrow().filter_by {
+            FilterBy::HighestLevelRequired => FilterBy::LowestLevelRequired,
+            FilterBy::LowestLevelRequired => FilterBy::HighestLevelRequired,
+            FilterBy::ItemName(item_name) => {
+                info!(item = %item_name, "Skill item chosen by name, keeping it.");
+                return;
+            }
+        };
+        self.send(SupervisorCommand::SetFilterBy(next_filter_by));
+    }
+
+    /// Returns `false` once the user asked to quit.
+    fn handle_key(&mut self, key_code: KeyCode) -> bool {
+        match key_code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.table_state.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table_state.select_previous(),
+            KeyCode::Char('p') => self.send(SupervisorCommand::Pause),
+            KeyCode::Char('r') => self.send(SupervisorCommand::Resume),
+            KeyCode::Char('s') => self.cycle_strategy(),
+            KeyCode::PageUp => self.log_scroll = self.log_scroll.saturating_add(10),
+            KeyCode::PageDown => self.log_scroll = self.log_scroll.saturating_sub(10),
+            _ => {}
+        }
+        true
+    }
+
+    fn account_row(account_state: &AccountState) -> Row<'static> {
+        let character_info = &account_state.character_info;
+        let (action_text, progress_text) = match &account_state.active_action {
+            Some(active_action) => (
+                format!("{} {}", active_action.skill_type, active_action.item_name),
+                format!("{:.0}%", active_action.current_progress),
+            ),
+            None => ("idle".to_string(), String::new()),
+        };
+        let countdown_text = account_state
+            .remaining_time()
+            .map(|remaining_time| {
+                let total_secs = remaining_time.num_seconds();
+                format!("{}:{:02}", total_secs / 60, total_secs % 60)
+            })
+            .unwrap_or_default();
+
+        Row::new(vec![
+            account_state.email.clone(),
+            character_info.name.clone(),
+            format!("{:?}", account_state.status),
+            account_state.location_name.clone(),
+            action_text,
+            progress_text,
+            countdown_text,
+            character_info.gold.to_string(),
+            character_info.tokens.to_string(),
+            character_info.shards.to_string(),
+            format!("{:?}", account_state.filter_by),
+        ])
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [table_area, skills_area, log_area, help_area] = Layout::vertical([
+            Constraint::Percentage(40),
+            Constraint::Length(3),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let account_states: Vec<AccountState> = self
+            .handles
+            .iter()
+            .map(|handle| handle.state.borrow().clone())
+            .collect();
+
+        let header = Row::new(vec![
+            "Account",
+            "Character",
+            "Status",
+            "Location",
+            "Action",
+            "Progress",
+            "ETA",
+            "Gold",
+            "Tokens",
+            "Shards",
+            "Strategy",
+        ])
+        .style(Style::new().add_modifier(Modifier::BOLD));
+        let table = Table::new(
+            account_states.iter().map(Self::account_row),
+            [
+                Constraint::Length(18),
+                Constraint::Length(14),
+                Constraint::Length(8),
+                Constraint::Length(16),
+                Constraint::Fill(1),
+                Constraint::Length(8),
+                Constraint::Length(7),
+                Constraint::Length(10),
+                Constraint::Length(7),
+                Constraint::Length(7),
+                Constraint::Length(20),
+            ],
+        )
+        .header(header)
+        .row_highlight_style(Style::new().reversed())
+        .block(Block::bordered().title(" Accounts "));
+        frame.render_stateful_widget(table, table_area, &mut self.table_state);
+
+        let selected_state = self
+            .table_state
+            .selected()
+            .and_then(|index| account_states.get(index));
+        let skills_text = selected_state
+            .map(|account_state| {
+                let mut skill_levels: Vec<String> = account_state
+                    .character_info
+                    .skill_level
+                    .iter()
+                    .map(|(skill_type, level)| format!("{skill_type} {level}"))
+                    .collect();
+                skill_levels.insert(
+                    0,
+                    format!("Combat {}", account_state.character_info.combat_level),
+                );
+                if let Some(last_error) = &account_state.last_error {
+                    skill_levels.push(format!("| last error: {last_error}"));
+                }
+                skill_levels.join("  ")
+            })
+            .unwrap_or_default();
+        frame.render_widget(
+            Paragraph::new(skills_text).block(Block::bordered().title(" Skills ")),
+            skills_area,
+        );
+
+        let log_lines = self.log_buffer.lines();
+        let visible_lines = log_area.height.saturating_sub(2) as usize;
+        self.log_scroll = self
+            .log_scroll
+            .min(log_lines.len().saturating_sub(visible_lines));
+        let log_end = log_lines.len() - self.log_scroll;
+        let log_start = log_end.saturating_sub(visible_lines);
+        let log_text: Vec<Line> = log_lines[log_start..log_end]
+            .iter()
+            .map(|line| Line::raw(line.clone()))
+            .collect();
+        frame.render_widget(
+            Paragraph::new(log_text).block(Block::bordered().title(" Events ")),
+            log_area,
+        );
+
+        frame.render_widget(
+            Paragraph::new(
+                " ↑/↓ select   p pause   r resume   s switch strategy   PgUp/PgDn scroll log   q quit",
+            )
+            .dim(),
+            help_area,
+        );
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if event::poll(TICK_RATE)?
+                && let Event::Key(key_event) = event::read()?
+                && key_event.kind == KeyEventKind::Press
+                && !self.handle_key(key_event.code)
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Shows the full-screen dashboard until the user quits, then hands the supervisors back.
+pub async fn run_dashboard(
+    handles: Vec<SupervisorHandle>,
+    log_buffer: LogBuffer,
+) -> Result<Vec<SupervisorHandle>> {
+    let mut dashboard = Dashboard {
+        handles,
+        log_buffer,
+        table_state: TableState::default().with_selected(0),
+        log_scroll: 0,
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let mut terminal = ratatui::init();
+        let dashboard_result = dashboard.run(&mut terminal);
+        ratatui::restore();
+        dashboard_result.map(|()| dashboard.handles)
+    })
+    .await
+    .map_err(|e| AppError::Application(e.to_string()))?
+}
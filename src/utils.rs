@@ -1,10 +1,13 @@
+use std::path::Path;
+
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as BASE64_STD;
 use once_cell::sync::OnceCell;
 use regex::Regex;
 
-use crate::models::location::Location;
-use crate::models::{FilterBy, SkillConfig, SkillItem};
+use crate::error::Result;
+use crate::models::location::WorldLocation;
+use crate::models::{CharacterInfo, Dungeon, FilterBy, SkillConfig, SkillItem};
 
 pub const API_VERSION: &str = "1.0.0.1";
 
@@ -45,42 +48,67 @@ pub fn obfuscate_email(email: &str) -> String {
     )
 }
 
+/// Writes through a temporary file so readers never see a partial file.
+pub async fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let temporary_path = path.with_extension("tmp");
+    tokio::fs::write(&temporary_path, contents).await?;
+    tokio::fs::rename(&temporary_path, path).await?;
+    Ok(())
+}
+
 fn find_best_skill_for_location<'a>(
-    location: &'a Location,
+    location: &'a WorldLocation,
     config: &SkillConfig,
 ) -> Option<&'a SkillItem> {
     let skills = &location.skill_items;
 
-    let skills_of_type = skills
+    let mut skills_of_type = skills
         .iter()
         .filter(|skill_item| skill_item.skill_type == config.skill_type);
 
-    match config.filter_by {
+    match &config.filter_by {
         FilterBy::HighestLevelRequired => {
             skills_of_type.max_by_key(|skill_item| skill_item.level_required)
         }
         FilterBy::LowestLevelRequired => {
             skills_of_type.min_by_key(|skill_item| skill_item.level_required)
         }
-        _ => unimplemented!(),
+        FilterBy::ItemName(item_name) => skills_of_type.find(|skill_item| {
+            skill_item
+                .name
+                .as_deref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(item_name))
+        }),
     }
 }
 
 pub fn find_best_skill<'a>(
-    locations: &'a [Location],
+    locations: &'a [WorldLocation],
     config: &SkillConfig,
-) -> Option<(&'a Location, &'a SkillItem)> {
-    let best_skills_per_location = locations.iter().filter_map(|location| {
+) -> Option<(&'a WorldLocation, &'a SkillItem)> {
+    let mut best_skills_per_location = locations.iter().filter_map(|location| {
         find_best_skill_for_location(location, config).map(|skill_item| (location, skill_item))
     });
 
-    match config.filter_by {
+    match &config.filter_by {
         FilterBy::HighestLevelRequired => {
             best_skills_per_location.max_by_key(|(_, skill_item)| skill_item.level_required)
         }
         FilterBy::LowestLevelRequired => {
             best_skills_per_location.min_by_key(|(_, skill_item)| skill_item.level_required)
         }
-        _ => unimplemented!(),
+        FilterBy::ItemName(_) => best_skills_per_location.next(),
     }
 }
+
+pub fn find_best_dungeon<'a>(
+    dungeons: &'a [Dungeon],
+    character_info: &CharacterInfo,
+) -> Option<&'a Dungeon> {
+    dungeons
+        .iter()
+        .filter(|dungeon| dungeon.level_required <= character_info.combat_level)
+        .filter(|dungeon| dungeon.gold_cost <= character_info.gold)
+        .filter(|dungeon| !dungeon.is_on_cooldown())
+        .max_by_key(|dungeon| dungeon.level_required)
+}
@@ -0,0 +1,284 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::Result,
+    models::{
+        Dungeon, DungeonFetch,
+        location::{LocationFetch, TravelOrigin, WorldLocation},
+    },
+    utils::write_atomically,
+};
+
+/// Bumped whenever the layout of the cache file changes, so older files are refetched.
+const FORMAT_VERSION: u32 = 2;
+
+/// How far a location is and what teleporting there costs, from one travel origin.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct TravelCost {
+    distance: u64,
+    teleport_cost: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct TravelCosts {
+    origin: TravelOrigin,
+    /// Per location id.
+    costs: BTreeMap<u64, TravelCost>,
+}
+
+/// Locations with their enemies, dungeons and skill items and the dungeon details, as
+/// the game returns them, before any per-character filtering. Travel costs depend on
+/// where a character stands, so they are kept apart per travel origin.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct WorldData {
+    format_version: u32,
+    /// Build the data was fetched from, when the game page revealed it.
+    game_version: Option<String>,
+    fetched_at: DateTime<Utc>,
+    /// Without `distance` and `teleport_cost`, see `travel_costs`.
+    locations: Vec<WorldLocation>,
+    travel_costs: Vec<TravelCosts>,
+    /// Without cooldowns; `None` until dungeons were first fetched.
+    dungeons: Option<Vec<Dungeon>>,
+}
+
+impl WorldData {
+    fn new(game_version: Option<&str>, locations: &[WorldLocation]) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            game_version: game_version.map(str::to_string),
+            fetched_at: Utc::now(),
+            locations: locations
+                .iter()
+                .map(|location| WorldLocation {
+                    distance: 0,
+                    teleport_cost: 0,
+                    ..location.clone()
+                })
+                .collect(),
+            travel_costs: vec![],
+            dungeons: None,
+        }
+    }
+
+    /// The cached locations as seen from `origin`, if their travel costs are known.
+    fn locations_from(&self, origin: TravelOrigin) -> Option<Vec<WorldLocation>> {
+        let travel_costs = self
+            .travel_costs
+            .iter()
+            .find(|travel_costs| travel_costs.origin == origin)?;
+        self.locations
+            .iter()
+            .map(|location| {
+                let travel_cost = travel_costs.costs.get(&location.id)?;
+                Some(WorldLocation {
+                    distance: travel_cost.distance,
+                    teleport_cost: travel_cost.teleport_cost,
+                    ..location.clone()
+                })
+            })
+            .collect()
+    }
+
+    fn record_travel_costs(&mut self, origin: TravelOrigin, locations: &[WorldLocation]) {
+        let costs = locations
+            .iter()
+            .map(|location| {
+                let travel_cost = TravelCost {
+                    distance: location.distance,
+                    teleport_cost: location.teleport_cost,
+                };
+                (location.id, travel_cost)
+            })
+            .collect();
+        self.travel_costs
+            .retain(|travel_costs| travel_costs.origin != origin);
+        self.travel_costs.push(TravelCosts { origin, costs });
+    }
+
+    /// Why the data cannot be used for `game_version`, if it cannot.
+    fn staleness(&self, game_version: Option<&str>, ttl: Duration) -> Option<String> {
+        if self.format_version != FORMAT_VERSION {
+            return Some(format!("format version {}", self.format_version));
+        }
+        if let (Some(cached_version), Some(game_version)) =
+            (self.game_version.as_deref(), game_version)
+            && cached_version != game_version
+        {
+            return Some(format!("game version {cached_version} -> {game_version}"));
+        }
+        let age = (Utc::now() - self.fetched_at).to_std().unwrap_or_default();
+        (age >= ttl).then(|| format!("older than {}s", ttl.as_secs()))
+    }
+}
+
+#[derive(Debug)]
+struct WorldCacheOptions {
+    path: PathBuf,
+    ttl: Duration,
+}
+
+/// World data shared by every account, in memory and in a file, once enabled.
+#[derive(Default)]
+pub struct WorldCache {
+    options: OnceCell<WorldCacheOptions>,
+    /// Held while fetching, so accounts loading together fetch only once.
+    world_data: Mutex<Option<WorldData>>,
+}
+
+pub static WORLD_CACHE: Lazy<WorldCache> = Lazy::new(WorldCache::default);
+
+impl WorldCache {
+    pub fn enable(&self, path: &Path, ttl: Duration) -> Result<()> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let options = WorldCacheOptions {
+            path: path.to_path_buf(),
+            ttl,
+        };
+        if self.options.set(options).is_ok() {
+            info!(path = %path.display(), ttl_secs = ttl.as_secs(), "Caching world data.");
+        }
+        Ok(())
+    }
+
+    /// All locations as seen from `origin`, from the cache while it is fresh, matches
+    /// `game_version` and knows the travel costs from `origin`, otherwise from `fetch`.
+    /// A complete fetch is cached, a partial one is returned without being cached.
+    pub async fn locations<F, Fut>(
+        &self,
+        game_version: Option<&str>,
+        origin: TravelOrigin,
+        fetch: F,
+    ) -> Result<LocationFetch>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<LocationFetch>>,
+    {
+        let Some(options) = self.options.get() else {
+            return fetch().await;
+        };
+
+        let mut world_data = self.world_data.lock().await;
+        if world_data.is_none() {
+            *world_data = Self::read(&options.path).await;
+        }
+        if let Some(cached_data) = world_data.as_ref() {
+            match cached_data.staleness(game_version, options.ttl) {
+                None => match cached_data.locations_from(origin) {
+                    Some(locations) => {
+                        debug!(
+                            locations = locations.len(),
+                            fetched_at = %cached_data.fetched_at,
+                            "Using cached world data."
+                        );
+                        return Ok(LocationFetch {
+                            locations,
+                            failures: vec![],
+                        });
+                    }
+                    None => debug!(?origin, "No cached travel costs from here, refetching."),
+                },
+                Some(reason) => {
+                    info!(%reason, "Cached world data is stale, refetching.");
+                    *world_data = None;
+                }
+            }
+        }
+
+        let location_fetch = fetch().await?;
+        if !location_fetch.is_complete() {
+            return Ok(location_fetch);
+        }
+        let cached_data = world_data
+            .get_or_insert_with(|| WorldData::new(game_version, &location_fetch.locations));
+        cached_data.record_travel_costs(origin, &location_fetch.locations);
+        Self::write(&options.path, cached_data).await;
+        Ok(location_fetch)
+    }
+
+    /// Every dungeon, from the cache while the world data it belongs to is fresh,
+    /// otherwise from `fetch`. A complete fetch is cached alongside fresh world data,
+    /// a partial one is returned without being cached.
+    pub async fn dungeons<F, Fut>(
+        &self,
+        game_version: Option<&str>,
+        fetch: F,
+    ) -> Result<DungeonFetch>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<DungeonFetch>>,
+    {
+        let Some(options) = self.options.get() else {
+            return fetch().await;
+        };
+
+        let mut world_data = self.world_data.lock().await;
+        if world_data.is_none() {
+            *world_data = Self::read(&options.path).await;
+        }
+        let fresh_data = world_data
+            .as_mut()
+            .filter(|cached_data| cached_data.staleness(game_version, options.ttl).is_none());
+        if let Some(cached_data) = &fresh_data
+            && let Some(dungeons) = &cached_data.dungeons
+        {
+            debug!(dungeons = dungeons.len(), "Using cached dungeons.");
+            return Ok(DungeonFetch {
+                dungeons: dungeons.clone(),
+                failures: vec![],
+            });
+        }
+
+        let dungeon_fetch = fetch().await?;
+        if let Some(cached_data) = fresh_data
+            && dungeon_fetch.is_complete()
+        {
+            cached_data.dungeons = Some(dungeon_fetch.dungeons.clone());
+            Self::write(&options.path, cached_data).await;
+        }
+        Ok(dungeon_fetch)
+    }
+
+    async fn write(path: &Path, world_data: &WorldData) {
+        let write_result = match serde_json::to_vec(world_data) {
+            Ok(world_json) => write_atomically(path, &world_json).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = write_result {
+            warn!(path = %path.display(), error = %e, "Failed to write world cache.");
+        }
+    }
+
+    async fn read(path: &Path) -> Option<WorldData> {
+        let raw_world_data = match tokio::fs::read(path).await {
+            Ok(raw_world_data) => raw_world_data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Failed to read world cache.");
+                return None;
+            }
+        };
+        match serde_json::from_slice(&raw_world_data) {
+            Ok(world_data) => Some(world_data),
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Ignoring unreadable world cache.");
+                None
+            }
+        }
+    }
+}